@@ -0,0 +1,125 @@
+use asterisk_queue_handler_events::{
+    asterisk::event::{AmiMessage, EventGenMap, EventHandler},
+    io::codec::AmiCodec,
+};
+use bytes::BytesMut;
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use futures::StreamExt;
+use tokio_util::codec::Decoder;
+
+const EVENTS: usize = 2000;
+
+fn session(newline: &str) -> Vec<u8> {
+    let mut data = format!("Asterisk Call Manager/7.0.3{newline}");
+    for i in 0..EVENTS {
+        for line in [
+            "Event: QueueMemberStatus",
+            "Privilege: agent,all",
+            "Queue: ventas",
+            &format!("MemberName: Agent {i}"),
+            &format!("Interface: PJSIP/{i}"),
+            &format!("StateInterface: PJSIP/{i}"),
+            "Membership: static",
+            "Penalty: 0",
+            "CallsTaken: 3",
+            "LastCall: 1700000000",
+            "LastPause: 0",
+            "InCall: 0",
+            "Status: 1",
+            "Paused: 0",
+            "PausedReason: ",
+            "Ringinuse: 0",
+        ] {
+            data.push_str(line);
+            data.push_str(newline);
+        }
+        data.push_str(newline);
+    }
+    data.into_bytes()
+}
+
+fn codec(data: &[u8], parse: bool) -> usize {
+    let mut codec = AmiCodec::new();
+    let mut src = BytesMut::from(data);
+    let mut count = 0;
+    while let Some(frame) = codec.decode_eof(&mut src).unwrap() {
+        if parse {
+            let frame = std::str::from_utf8(&frame).unwrap();
+            if AmiMessage::try_from(EventGenMap::gen_map(frame)).is_ok() {
+                count += 1;
+            }
+        } else {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Framing of the EventHandler before AmiCodec: reads of 1 KiB appended to a Vec,
+/// search of the CRLF blank line from the last position and a copy of every frame
+fn old_framing(data: &[u8], parse: bool) -> usize {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut processed = 0;
+    let mut greeted = false;
+    let mut count = 0;
+    for chunk in data.chunks(1024) {
+        buffer.extend_from_slice(chunk);
+        if !greeted {
+            let Some(pos) = buffer.windows(2).position(|x| x == b"\r\n") else {
+                continue;
+            };
+            greeted = true;
+            buffer.drain(..pos + 2);
+        }
+
+        while let Some(pos) = buffer[processed..]
+            .windows(4)
+            .position(|x| x == b"\r\n\r\n")
+        {
+            let frame = buffer.drain(..=processed + pos + 3).collect::<Vec<u8>>();
+            processed = 0;
+            let frame = std::str::from_utf8(&frame).unwrap().trim_end();
+            if !parse || AmiMessage::try_from(EventGenMap::gen_map(frame)).is_ok() {
+                count += 1;
+            }
+        }
+        processed = buffer.len().saturating_sub(4);
+    }
+    count
+}
+
+async fn event_handler(data: &[u8]) -> usize {
+    EventHandler::from_split(data, tokio::io::sink(), String::new(), String::new())
+        .filter(|msg| std::future::ready(msg.is_ok()))
+        .count()
+        .await
+}
+
+fn framing(c: &mut Criterion) {
+    let crlf = session("\r\n");
+    let lf = session("\n");
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+
+    // Every variant frames the same events, the codec and the EventHandler count the banner too
+    assert_eq!(old_framing(&crlf, true), EVENTS);
+    assert_eq!(codec(&crlf, true), EVENTS);
+    assert_eq!(codec(&lf, false), EVENTS + 1);
+    assert_eq!(runtime.block_on(event_handler(&crlf)), EVENTS + 1);
+
+    let mut group = c.benchmark_group("framing");
+    group.throughput(Throughput::Bytes(crlf.len() as u64));
+
+    group.bench_function("old_framing", |b| b.iter(|| old_framing(&crlf, false)));
+    group.bench_function("old_framing_parse", |b| b.iter(|| old_framing(&crlf, true)));
+    group.bench_function("codec", |b| b.iter(|| codec(&crlf, false)));
+    group.bench_function("codec_bare_lf", |b| b.iter(|| codec(&lf, false)));
+    group.bench_function("codec_parse", |b| b.iter(|| codec(&crlf, true)));
+    group.bench_function("event_handler", |b| {
+        b.to_async(&runtime).iter(|| event_handler(&crlf))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, framing);
+criterion_main!(benches);
@@ -3,7 +3,9 @@ use quote::quote;
 use syn::{Data, DeriveInput, Expr,Lit, Meta, parse::Parser, punctuated::Punctuated, token::Comma};
 
 
-#[proc_macro_derive(ParserEvent, attributes(parser, use_parse, key, skip_with_defaut))]
+/// `use_parse`: the value is parsed with FromStr, an invalid one is the default
+/// `required`: the frame is rejected when the value is missing, or invalid with `use_parse`
+#[proc_macro_derive(ParserEvent, attributes(parser, use_parse, key, skip_with_defaut, required, with))]
 pub fn parse_input_derive_macro(input: TokenStream) -> TokenStream {
     let ast: DeriveInput = syn::parse(input).unwrap();
 
@@ -29,15 +31,37 @@ fn impl_parse_input_trait(ast: DeriveInput) -> TokenStream {
                     
                     let parsed_tokens = Punctuated::<Meta, Comma>::parse_terminated.parse2(meta_list.tokens.clone()).unwrap();
                     let mut parser = false;
+                    let mut required = false;
+                    let mut variables = false;
+                    let mut flatten = false;
+                    let mut prefix: Option<String> = None;
+                    let mut with: Option<syn::Path> = None;
                     let mut key: Option<Vec<String>> = None;
                     for nested in parsed_tokens {
                         match &nested {
                             Meta::Path(path) if path.is_ident("use_parse") => {
                                 parser = true;
                             },
+                            Meta::Path(path) if path.is_ident("required") => {
+                                required = true;
+                            },
+                            Meta::Path(path) if path.is_ident("variables") => {
+                                variables = true;
+                            },
+                            Meta::Path(path) if path.is_ident("flatten") => {
+                                flatten = true;
+                            },
                             Meta::NameValue(meta_name_value) => {
 
-                                if meta_name_value.path.is_ident("key") {
+                                if meta_name_value.path.is_ident("with") {
+                                    if let Expr::Lit(e) = &meta_name_value.value && let Lit::Str(value) = &e.lit {
+                                        with = Some(value.parse().expect("Invalid path in with"));
+                                    }
+                                } else if meta_name_value.path.is_ident("prefix") {
+                                    if let Expr::Lit(e) = &meta_name_value.value && let Lit::Str(value) = &e.lit {
+                                        prefix = Some(value.value());
+                                    }
+                                } else if meta_name_value.path.is_ident("key") {
                                     if let Expr::Lit(e) = &meta_name_value.value && let Lit::Str(value) = &e.lit {
                                         if let Some(key) = key.as_mut() {
                                             key.push(value.value());
@@ -51,19 +75,73 @@ fn impl_parse_input_trait(ast: DeriveInput) -> TokenStream {
                         }
                     }
 
+                    if flatten {
+                        let data = match prefix {
+                            Some(prefix) => quote! { data.prefixed(#prefix) },
+                            None => quote! { data.clone() },
+                        };
+                        // Option<T> is None when T can't be parsed
+                        return match option_inner(&x.ty) {
+                            Some(ty) => quote! {
+                                #ident: <#ty as crate::asterisk::event::ParserEvent>::parse_from_map(#data).ok()
+                            },
+                            None => {
+                                let ty = &x.ty;
+                                quote! {
+                                    #ident: <#ty as crate::asterisk::event::ParserEvent>::parse_from_map(#data)?
+                                }
+                            }
+                        };
+                    }
+
                     let key = key.filter(|x| !x.is_empty()).expect("Key not defined");
 
-                    if parser {
-                        
+                    if variables {
+                        return quote! {
+                            #ident: [#(#key),*]
+                                .into_iter()
+                                .flat_map(|x| data.variables(x))
+                                .map(|(name, value)| (name.to_string(), value.to_string()))
+                                .collect()
+                        };
+                    }
+
+                    let default = if required {
+                        let first = &key[0];
                         quote! {
-                            #ident: vec![#(#key),*].into_iter().find_map(|x| data.remove(x).and_then(|x| x.parse().ok())).unwrap_or_default()
+                            return Err(crate::asterisk::error::AmiError::MissingField(#first.to_string()))
                         }
                     } else {
-                        let ty = &x.ty;                        
+                        quote! { Default::default() }
+                    };
+
+                    let key_pattern = if parser && required && with.is_none() {
+                        quote! { key }
+                    } else {
+                        quote! { _ }
+                    };
+
+                    let value = if let Some(with) = with {
+                        quote! { #with(value) }
+                    } else if parser && required {
                         quote! {
-                            #ident: {
-                                vec![#(#key),*].into_iter().find_map(|x| data.remove(x)).map(#ty::from).unwrap_or_default()
-                            }
+                            value.parse().map_err(|_| crate::asterisk::error::AmiError::FieldParse {
+                                key: key.to_string(),
+                                value: value.to_string(),
+                            })?
+                        }
+                    } else if parser {
+                        // Only a required field rejects the frame, the others take the default
+                        quote! { value.parse().unwrap_or_default() }
+                    } else {
+                        let ty = &x.ty;
+                        quote! { #ty::from(value) }
+                    };
+
+                    quote! {
+                        #ident: match [#(#key),*].into_iter().find_map(|x| data.get_all(x).find(|x| !x.is_empty()).map(|v| (x, v))) {
+                            Some((#key_pattern, value)) => #value,
+                            None => #default,
                         }
                     }
                 } else {
@@ -78,11 +156,25 @@ fn impl_parse_input_trait(ast: DeriveInput) -> TokenStream {
     
     quote::quote! {
         impl crate::asterisk::event::ParserEvent for #ident {
-            fn parse_from_map(mut data: std::collections::HashMap<&str, &str>) -> #ident {
-                #ident {
+            fn parse_from_map(data: crate::asterisk::headers::Headers<'_>) -> Result<#ident, crate::asterisk::error::AmiError> {
+                Ok(#ident {
                     #(#fields),*
-                }
+                })
             }
         }
     }.into()
-}
\ No newline at end of file
+}
+
+fn option_inner(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last().filter(|x| x.ident == "Option")?;
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+
+use tokio::sync::oneshot;
+
+use crate::asterisk::{
+    entities::{ResponseAmi, ResponseAmyType},
+    error::AmiError,
+    event::AmiMessage,
+};
+
+/// Event raised after a successful action that doesn't carry the ActionID.
+/// It is recognized by the queue and the interface of the member
+#[derive(Debug, Clone, PartialEq)]
+pub struct FollowUp {
+    pub event: FollowUpEvent,
+    pub queue: Option<String>,
+    pub interface: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FollowUpEvent {
+    MemberPaused,
+    MemberAdded,
+    MemberRemoved,
+}
+
+impl FollowUp {
+    /// If `queue` is None the first event of any queue resolves the action
+    fn matches(&self, message: &AmiMessage) -> bool {
+        let member = match (self.event, message) {
+            (FollowUpEvent::MemberPaused, AmiMessage::MemberPaused(member))
+            | (FollowUpEvent::MemberAdded, AmiMessage::MemberAdded(member))
+            | (FollowUpEvent::MemberRemoved, AmiMessage::MemberRemoved(member)) => member,
+            _ => return false,
+        };
+
+        member.interface == self.interface
+            && self.queue.as_ref().is_none_or(|queue| *queue == member.queue)
+    }
+}
+
+/// Result of an action sent to the AMI.
+///
+/// response: the `Response` with the same ActionID
+/// events: the event list that follows the response (e.g. QueueStatus), empty otherwise
+#[derive(Debug)]
+pub struct ActionResponse {
+    pub response: ResponseAmi,
+    pub events: Vec<AmiMessage>,
+}
+
+/// Value of the `EventList` header
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventList {
+    None,
+    Start,
+    Complete,
+}
+
+impl From<Option<&str>> for EventList {
+    fn from(value: Option<&str>) -> Self {
+        match value.map(str::to_ascii_lowercase).as_deref() {
+            Some("start") => Self::Start,
+            Some("complete") => Self::Complete,
+            _ => Self::None,
+        }
+    }
+}
+
+struct PendingAction {
+    r#type: ResponseAmyType,
+    tx: Option<oneshot::Sender<ActionResponse>>,
+    follow_up: Option<FollowUp>,
+    response: Option<ResponseAmi>,
+    events: Vec<AmiMessage>,
+}
+
+impl PendingAction {
+    fn resolve(self) {
+        if let (Some(tx), Some(response)) = (self.tx, self.response) {
+            _ = tx.send(ActionResponse {
+                response,
+                events: self.events,
+            });
+        }
+    }
+}
+
+/// Actions sent that are waiting for their response
+#[derive(Default)]
+pub struct PendingActions {
+    next_id: u64,
+    actions: HashMap<String, PendingAction>,
+}
+
+impl PendingActions {
+    /// Register a new action and returns the ActionID to send with it.
+    /// If `tx` is None the response and its events are delivered by the stream.
+    /// The actions nobody waits for anymore (e.g. the send timed out) are removed,
+    /// so their FollowUp doesn't take the event of a new action
+    pub fn register(
+        &mut self,
+        r#type: ResponseAmyType,
+        tx: Option<oneshot::Sender<ActionResponse>>,
+        follow_up: Option<FollowUp>,
+    ) -> String {
+        self.actions
+            .retain(|_, x| x.tx.as_ref().is_none_or(|tx| !tx.is_closed()));
+
+        self.next_id += 1;
+        let id = format!("alma-{}", self.next_id);
+        self.actions.insert(
+            id.clone(),
+            PendingAction {
+                r#type,
+                tx,
+                follow_up,
+                response: None,
+                events: Vec::new(),
+            },
+        );
+        id
+    }
+
+    pub fn r#type(&self, id: &str) -> ResponseAmyType {
+        self.actions
+            .get(id)
+            .map(|x| x.r#type.clone())
+            .unwrap_or_default()
+    }
+
+    /// Routes the message to the action that generated it.
+    /// Returns the message when it must be delivered by the stream
+    pub fn dispatch(
+        &mut self,
+        id: &str,
+        message: AmiMessage,
+        list: EventList,
+    ) -> Option<AmiMessage> {
+        let Some(action) = self.actions.get_mut(id) else {
+            return Some(message);
+        };
+
+        match message {
+            AmiMessage::Response(response) => {
+                let wait_list = list == EventList::Start && response.is_ok();
+
+                if action.tx.is_none() {
+                    if !wait_list {
+                        self.actions.remove(id);
+                    }
+                    return Some(AmiMessage::Response(response));
+                }
+
+                let wait_follow_up = action.follow_up.is_some() && response.is_ok();
+                action.response = Some(response);
+                if !wait_list
+                    && !wait_follow_up
+                    && let Some(action) = self.actions.remove(id)
+                {
+                    action.resolve();
+                }
+                None
+            }
+            message => {
+                let complete = list == EventList::Complete;
+
+                if action.tx.is_none() {
+                    if complete {
+                        self.actions.remove(id);
+                    }
+                    return Some(message);
+                }
+
+                action.events.push(message);
+                if complete && let Some(action) = self.actions.remove(id) {
+                    action.resolve();
+                }
+                None
+            }
+        }
+    }
+
+    /// Give a copy of an event without ActionID to the action waiting for it
+    pub fn follow_up(&mut self, message: &AmiMessage) {
+        let id = self.actions.iter().find_map(|(id, action)| {
+            action
+                .follow_up
+                .as_ref()
+                .filter(|x| action.response.is_some() && x.matches(message))
+                .map(|_| id.clone())
+        });
+
+        if let Some(mut action) = id.and_then(|id| self.actions.remove(&id)) {
+            action.events.push(message.clone());
+            action.resolve();
+        }
+    }
+
+    /// Drop the action, its receiver gets a `RecvError`. Returns the events it received
+    pub fn cancel(&mut self, id: &str) -> Option<usize> {
+        self.actions.remove(id).map(|x| x.events.len())
+    }
+
+    /// Drop every pending action, the receivers get a `RecvError`
+    pub fn clear(&mut self) {
+        self.actions.clear();
+    }
+}
+
+/// Generic AMI action, e.g. `Action::new("Ping")`
+#[derive(Debug, Clone)]
+pub struct Action {
+    pub name: String,
+    pub headers: Vec<(String, String)>,
+    pub follow_up: Option<FollowUp>,
+}
+
+impl Action {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            headers: Vec::new(),
+            follow_up: None,
+        }
+    }
+
+    /// Header only sent when `value` is Some
+    pub fn header_opt(self, key: impl Into<String>, value: Option<impl Into<String>>) -> Self {
+        match value {
+            Some(value) => self.header(key, value),
+            None => self,
+        }
+    }
+
+    /// The response is not resolved until this event arrives
+    pub fn follow_up(mut self, follow_up: FollowUp) -> Self {
+        self.follow_up = Some(follow_up);
+        self
+    }
+
+    pub fn header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((key.into(), value.into()));
+        self
+    }
+
+    /// Fails with AmiError::InvalidHeader when a key or a value has a CR or a LF
+    pub fn validate(&self) -> Result<(), AmiError> {
+        let line_break = |x: &str| x.contains(['\r', '\n']);
+        let headers = std::iter::once(("Action", self.name.as_str()))
+            .chain(self.headers.iter().map(|(key, value)| (key.as_str(), value.as_str())));
+
+        for (key, value) in headers {
+            if line_break(key) || line_break(value) {
+                return Err(AmiError::InvalidHeader {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Headers of the action without the ActionID and the final blank line
+    pub fn to_frame(&self) -> String {
+        let mut frame = format!("Action: {}\r\n", self.name);
+        for (key, value) in &self.headers {
+            frame.push_str(key);
+            frame.push_str(": ");
+            frame.push_str(value);
+            frame.push_str("\r\n");
+        }
+        frame
+    }
+}
@@ -0,0 +1,739 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crate::asterisk::{
+    entities::{
+        agent::{AgentComplete, AgentConnect},
+        bridge::BridgeChannel,
+        channel::{Channel, Dial, Hangup},
+        transfer::{AttendedTransfer, BlindTransfer},
+    },
+    event::AmiMessage,
+};
+
+/// Finished calls kept to answer what happened after a caller left the queue
+const FINISHED_CALLS: usize = 256;
+
+/// Calls, agent calls and bridges in progress kept at most.
+/// When the event that ends one is lost the oldest are discarded
+pub const ACTIVE_CALLS: usize = 4096;
+
+/// Channel of a call
+///
+/// state: ChannelStateDesc of the last Newchannel / Newstate
+/// hangup: Cause and Cause-txt of the Hangup
+#[derive(Debug, Clone)]
+pub struct CallChannel {
+    pub channel: String,
+    pub unique_id: String,
+    pub state: String,
+    pub created_at: Instant,
+    pub hangup: Option<(u16, String)>,
+    pub hungup_at: Option<Instant>,
+}
+
+impl From<&Channel> for CallChannel {
+    fn from(value: &Channel) -> Self {
+        Self {
+            channel: value.channel.clone(),
+            unique_id: value.unique_id.clone(),
+            state: value.state_desc.clone(),
+            created_at: Instant::now(),
+            hangup: None,
+            hungup_at: None,
+        }
+    }
+}
+
+/// DialBegin and its DialEnd
+///
+/// status: DialStatus, None while it's ringing
+#[derive(Debug, Clone)]
+pub struct DialAttempt {
+    pub caller: Option<String>,
+    pub dest: String,
+    pub dest_unique_id: String,
+    pub started_at: Instant,
+    pub ended_at: Option<Instant>,
+    pub status: Option<String>,
+}
+
+/// Time that two or more channels were together in a bridge.
+/// A new segment starts every time a channel enters or leaves the bridge,
+/// so after a transfer the talk time of each leg is separated
+///
+/// channels: Uniqueid and name of the channels in the bridge
+/// ended_at: None while they are talking
+#[derive(Debug, Clone)]
+pub struct TalkSegment {
+    pub bridge: String,
+    pub channels: Vec<(String, String)>,
+    pub started_at: Instant,
+    pub ended_at: Option<Instant>,
+}
+
+impl TalkSegment {
+    pub fn duration(&self) -> Duration {
+        self.ended_at
+            .unwrap_or_else(Instant::now)
+            .duration_since(self.started_at)
+    }
+
+    pub fn contains(&self, unique_id: &str) -> bool {
+        self.channels.iter().any(|(x, _)| x == unique_id)
+    }
+}
+
+/// Time on hold, from the first Hold / MusicOnHoldStart to the last Unhold / MusicOnHoldStop
+#[derive(Debug, Clone)]
+pub struct HoldSegment {
+    pub started_at: Instant,
+    pub ended_at: Option<Instant>,
+}
+
+impl HoldSegment {
+    pub fn duration(&self) -> Duration {
+        self.ended_at
+            .unwrap_or_else(Instant::now)
+            .duration_since(self.started_at)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransferKind {
+    Blind { context: String, extension: String },
+
+    /// DestType of the AttendedTransfer
+    Attended { dest_type: String },
+}
+
+/// Transfer of an agent call
+///
+/// result: Success, Fail, Invalid or Not Permitted
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub kind: TransferKind,
+    pub result: String,
+    pub at: Instant,
+}
+
+impl Transfer {
+    pub fn is_success(&self) -> bool {
+        self.result.eq_ignore_ascii_case("Success")
+    }
+}
+
+/// Queue call answered by an agent, from AgentConnect to AgentComplete.
+/// The holds and the transfers need the class `call`
+///
+/// caller_unique_id, agent_unique_id: Uniqueid and DestUniqueid of AgentConnect
+/// wait_time: HoldTime of AgentConnect, seconds in the queue
+/// talk_time: TalkTime of AgentComplete, the holds are included
+/// reason: Reason of AgentComplete, caller, agent or transfer
+#[derive(Debug, Clone)]
+pub struct AgentCall {
+    pub queue: String,
+    pub interface: String,
+    pub member_name: String,
+    pub caller_unique_id: String,
+    pub agent_unique_id: String,
+    pub ring_time: u64,
+    pub wait_time: u64,
+    pub connected_at: Instant,
+    pub completed_at: Option<Instant>,
+    pub talk_time: Option<u64>,
+    pub reason: String,
+    pub holds: Vec<HoldSegment>,
+    pub transfer: Option<Transfer>,
+    /// Channels on hold, true when it's the music on hold
+    holding: Vec<(String, bool)>,
+}
+
+impl AgentCall {
+    pub fn hold_time(&self) -> Duration {
+        self.holds.iter().map(HoldSegment::duration).sum()
+    }
+
+    /// Talk time without the holds
+    pub fn talk_time_without_hold(&self) -> Duration {
+        let talk = match self.talk_time {
+            Some(talk_time) => Duration::from_secs(talk_time),
+            None => self.connected_at.elapsed(),
+        };
+        talk.saturating_sub(self.hold_time())
+    }
+
+    fn has_channel(&self, unique_id: &str) -> bool {
+        self.caller_unique_id == unique_id || self.agent_unique_id == unique_id
+    }
+
+    fn hold(&mut self, unique_id: &str, music: bool, start: bool) {
+        let was_holding = !self.holding.is_empty();
+        let key = (unique_id.to_string(), music);
+
+        if start {
+            if !self.holding.contains(&key) {
+                self.holding.push(key);
+            }
+        } else {
+            self.holding.retain(|x| *x != key);
+        }
+
+        match (was_holding, self.holding.is_empty()) {
+            (false, false) => self.holds.push(HoldSegment {
+                started_at: Instant::now(),
+                ended_at: None,
+            }),
+            (true, true) => self.end_hold(),
+            _ => {}
+        }
+    }
+
+    fn end_hold(&mut self) {
+        self.holding.clear();
+        if let Some(hold) = self.holds.last_mut().filter(|x| x.ended_at.is_none()) {
+            hold.ended_at = Some(Instant::now());
+        }
+    }
+}
+
+impl From<&AgentConnect> for AgentCall {
+    fn from(value: &AgentConnect) -> Self {
+        Self {
+            queue: value.queue.clone(),
+            interface: value.interface.clone(),
+            member_name: value.member_name.clone(),
+            caller_unique_id: value.caller_unique_id.clone(),
+            agent_unique_id: value.dest_unique_id.clone(),
+            ring_time: value.ring_time,
+            wait_time: value.hold_time,
+            connected_at: Instant::now(),
+            completed_at: None,
+            talk_time: None,
+            reason: String::new(),
+            holds: Vec::new(),
+            transfer: None,
+            holding: Vec::new(),
+        }
+    }
+}
+
+impl From<&AgentComplete> for AgentCall {
+    fn from(value: &AgentComplete) -> Self {
+        Self {
+            queue: value.queue.clone(),
+            interface: value.interface.clone(),
+            member_name: value.member_name.clone(),
+            caller_unique_id: value.caller_unique_id.clone(),
+            agent_unique_id: value.dest_unique_id.clone(),
+            ring_time: 0,
+            wait_time: value.hold_time,
+            connected_at: Instant::now()
+                .checked_sub(Duration::from_secs(value.talk_time))
+                .unwrap_or_else(Instant::now),
+            completed_at: None,
+            talk_time: None,
+            reason: String::new(),
+            holds: Vec::new(),
+            transfer: None,
+            holding: Vec::new(),
+        }
+    }
+}
+
+/// Channels with the same Linkedid, from the first Newchannel to the last Hangup
+///
+/// channels: by Uniqueid, in order of creation
+/// queue: queue the caller entered, with the time of QueueCallerJoin and QueueCallerLeave
+/// talk: bridges with channels of the call, in order
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub linked_id: String,
+    pub channels: Vec<CallChannel>,
+    pub dials: Vec<DialAttempt>,
+    pub talk: Vec<TalkSegment>,
+    pub queue: Option<String>,
+    pub caller_unique_id: Option<String>,
+    pub created_at: Instant,
+    pub joined_at: Option<Instant>,
+    pub left_at: Option<Instant>,
+    pub ended_at: Option<Instant>,
+}
+
+impl Call {
+    fn new(linked_id: &str) -> Self {
+        Self {
+            linked_id: linked_id.to_string(),
+            channels: Vec::new(),
+            dials: Vec::new(),
+            talk: Vec::new(),
+            queue: None,
+            caller_unique_id: None,
+            created_at: Instant::now(),
+            joined_at: None,
+            left_at: None,
+            ended_at: None,
+        }
+    }
+
+    pub fn channel(&self, unique_id: &str) -> Option<&CallChannel> {
+        self.channels.iter().find(|x| x.unique_id == unique_id)
+    }
+
+    /// Time before entering the queue (IVR, announcements...)
+    pub fn time_before_queue(&self) -> Option<Duration> {
+        self.joined_at.map(|x| x.duration_since(self.created_at))
+    }
+
+    /// Time after leaving the queue until the end of the call
+    pub fn time_after_queue(&self) -> Option<Duration> {
+        let left = self.left_at?;
+        Some(self.ended_at.unwrap_or_else(Instant::now).duration_since(left))
+    }
+
+    /// Hangup cause of the channel that entered the queue, or of the first channel
+    pub fn hangup(&self) -> Option<&(u16, String)> {
+        self.caller_unique_id
+            .as_deref()
+            .and_then(|x| self.channel(x))
+            .or(self.channels.first())?
+            .hangup
+            .as_ref()
+    }
+
+    /// Time the channel was bridged with any other channel
+    pub fn talk_time(&self, unique_id: &str) -> Duration {
+        self.talk
+            .iter()
+            .filter(|x| x.contains(unique_id))
+            .map(TalkSegment::duration)
+            .sum()
+    }
+
+    /// Time the two channels were bridged together, e.g. the caller and one agent
+    pub fn talk_time_with(&self, unique_id: &str, other: &str) -> Duration {
+        self.talk
+            .iter()
+            .filter(|x| x.contains(unique_id) && x.contains(other))
+            .map(TalkSegment::duration)
+            .sum()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.ended_at.is_some()
+    }
+
+    fn channel_mut(&mut self, channel: &Channel) -> &mut CallChannel {
+        match self
+            .channels
+            .iter()
+            .position(|x| x.unique_id == channel.unique_id)
+        {
+            Some(index) => &mut self.channels[index],
+            None => {
+                self.channels.push(channel.into());
+                self.channels.last_mut().unwrap()
+            }
+        }
+    }
+}
+
+/// Calls of the PBX, built with the events of the class `call`.
+/// The queue callers are linked by their Uniqueid
+#[derive(Debug, Clone, Default)]
+pub struct Calls {
+    active: HashMap<String, Call>,
+    /// Uniqueid to Linkedid
+    channels: HashMap<String, String>,
+    /// Channels in each bridge, by BridgeUniqueid
+    bridges: HashMap<String, Vec<(String, String)>>,
+    finished: VecDeque<Call>,
+    /// By Uniqueid of the caller
+    agent_calls: HashMap<String, AgentCall>,
+    completed_agent_calls: VecDeque<AgentCall>,
+}
+
+impl Calls {
+    /// Call of a channel, the id can be the Uniqueid or the Linkedid
+    pub fn get(&self, id: &str) -> Option<&Call> {
+        let linked_id = self.channels.get(id).map(String::as_str).unwrap_or(id);
+        self.active.get(linked_id).or_else(|| {
+            self.finished
+                .iter()
+                .rev()
+                .find(|x| x.linked_id == id || x.channel(id).is_some())
+        })
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &Call> {
+        self.active.values()
+    }
+
+    pub fn finished(&self) -> impl Iterator<Item = &Call> {
+        self.finished.iter()
+    }
+
+    /// Agent call by the Uniqueid of the caller, the last one if it was answered more than once
+    pub fn agent_call(&self, unique_id: &str) -> Option<&AgentCall> {
+        self.agent_calls.get(unique_id).or_else(|| {
+            self.completed_agent_calls
+                .iter()
+                .rev()
+                .find(|x| x.caller_unique_id == unique_id)
+        })
+    }
+
+    /// Agent calls in progress
+    pub fn agent_calls(&self) -> impl Iterator<Item = &AgentCall> {
+        self.agent_calls.values()
+    }
+
+    pub fn completed_agent_calls(&self) -> impl Iterator<Item = &AgentCall> {
+        self.completed_agent_calls.iter()
+    }
+
+    /// Discard the calls, agent calls and bridges in progress, e.g. after a disconnection:
+    /// the events that end them can be lost. The finished calls are kept
+    pub fn clear_active(&mut self) {
+        self.active.clear();
+        self.channels.clear();
+        self.bridges.clear();
+        self.agent_calls.clear();
+    }
+
+    /// Update the calls, returns the Linkedid of the call that changed
+    pub fn apply(&mut self, message: &AmiMessage) -> Option<String> {
+        match message {
+            AmiMessage::Newchannel(channel) | AmiMessage::Newstate(channel) => {
+                let call = self.call(channel);
+                call.channel_mut(channel).state = channel.state_desc.clone();
+                Some(call.linked_id.clone())
+            }
+            AmiMessage::DialBegin(dial) => {
+                let call = self.call(&dial.dest);
+                call.channel_mut(&dial.dest);
+                call.dials.push(DialAttempt::from(dial));
+                Some(call.linked_id.clone())
+            }
+            AmiMessage::DialEnd(dial) => {
+                let call = self.call(&dial.dest);
+                if let Some(attempt) = call
+                    .dials
+                    .iter_mut()
+                    .rev()
+                    .find(|x| x.dest_unique_id == dial.dest.unique_id && x.status.is_none())
+                {
+                    attempt.status = Some(dial.dial_status.clone());
+                    attempt.ended_at = Some(Instant::now());
+                }
+                Some(call.linked_id.clone())
+            }
+            AmiMessage::Hangup(hangup) => self.hangup(hangup),
+            AmiMessage::AgentConnect(agent) => {
+                if !self.agent_calls.contains_key(&agent.caller_unique_id) {
+                    self.evict_agent_call();
+                }
+                self.agent_calls
+                    .insert(agent.caller_unique_id.clone(), agent.into());
+                self.channels.get(&agent.caller_unique_id).cloned()
+            }
+            AmiMessage::AgentComplete(agent) => self.agent_complete(agent),
+            AmiMessage::Hold(hold) => self.hold(&hold.channel, false, true),
+            AmiMessage::Unhold(hold) => self.hold(&hold.channel, false, false),
+            AmiMessage::MusicOnHoldStart(hold) => self.hold(&hold.channel, true, true),
+            AmiMessage::MusicOnHoldStop(hold) => self.hold(&hold.channel, true, false),
+            AmiMessage::BlindTransfer(transfer) => self.blind_transfer(transfer),
+            AmiMessage::AttendedTransfer(transfer) => self.attended_transfer(transfer),
+            AmiMessage::BridgeCreate(bridge) => {
+                if !self.bridges.contains_key(&bridge.unique_id) {
+                    self.evict_bridge();
+                }
+                self.bridges.entry(bridge.unique_id.clone()).or_default();
+                None
+            }
+            AmiMessage::BridgeEnter(enter) => self.bridge_changed(enter, true),
+            AmiMessage::BridgeLeave(leave) => self.bridge_changed(leave, false),
+            AmiMessage::BridgeDestroy(bridge) => {
+                let channels = self.bridges.remove(&bridge.unique_id)?;
+                self.end_segment(&bridge.unique_id, &channels)
+            }
+            AmiMessage::CallerJoin(caller) => {
+                let linked_id = self.channels.get(&caller.callet_unique_id)?.clone();
+                let call = self.active.get_mut(&linked_id)?;
+                call.queue = Some(caller.queue.clone());
+                call.caller_unique_id = Some(caller.callet_unique_id.clone());
+                call.joined_at = Some(Instant::now());
+                call.left_at = None;
+                Some(linked_id)
+            }
+            AmiMessage::CallerLeave(caller) => {
+                let linked_id = self.channels.get(&caller.callet_unique_id)?.clone();
+                self.active.get_mut(&linked_id)?.left_at = Some(Instant::now());
+                Some(linked_id)
+            }
+            _ => None,
+        }
+    }
+
+    /// Call of the channel, it's created when it's the first channel
+    fn call(&mut self, channel: &Channel) -> &mut Call {
+        let linked_id = self
+            .channels
+            .get(&channel.unique_id)
+            .cloned()
+            .unwrap_or_else(|| channel.call_id().to_string());
+        if !self.active.contains_key(&linked_id) {
+            self.evict_call();
+        }
+
+        self.channels
+            .insert(channel.unique_id.clone(), linked_id.clone());
+        self.active
+            .entry(linked_id.clone())
+            .or_insert_with(|| Call::new(&linked_id))
+    }
+
+    /// Make room for a new call, the oldest one lost its Hangup
+    fn evict_call(&mut self) {
+        if self.active.len() < ACTIVE_CALLS {
+            return;
+        }
+        let Some(oldest) = self
+            .active
+            .values()
+            .min_by_key(|x| x.created_at)
+            .map(|x| x.linked_id.clone())
+        else {
+            return;
+        };
+        self.active.remove(&oldest);
+        self.channels.retain(|_, linked_id| *linked_id != oldest);
+    }
+
+    /// Make room for a new agent call, the oldest one lost its AgentComplete
+    fn evict_agent_call(&mut self) {
+        if self.agent_calls.len() < ACTIVE_CALLS {
+            return;
+        }
+        if let Some(oldest) = self
+            .agent_calls
+            .iter()
+            .min_by_key(|(_, x)| x.connected_at)
+            .map(|(id, _)| id.clone())
+        {
+            self.agent_calls.remove(&oldest);
+        }
+    }
+
+    /// Make room for a new bridge, first the ones without channels of an active call
+    fn evict_bridge(&mut self) {
+        if self.bridges.len() < ACTIVE_CALLS {
+            return;
+        }
+        let channels = &self.channels;
+        self.bridges
+            .retain(|_, x| x.iter().any(|(unique_id, _)| channels.contains_key(unique_id)));
+
+        if self.bridges.len() >= ACTIVE_CALLS
+            && let Some(id) = self.bridges.keys().next().cloned()
+        {
+            self.bridges.remove(&id);
+        }
+    }
+
+    /// The segment of the bridge ends and a new one starts with the channels that remain
+    fn bridge_changed(&mut self, event: &BridgeChannel, enter: bool) -> Option<String> {
+        let id = &event.bridge.unique_id;
+        let mut channels = self.bridges.remove(id).unwrap_or_default();
+        let ended = self.end_segment(id, &channels);
+
+        channels.retain(|(x, _)| *x != event.channel.unique_id);
+        if enter {
+            channels.push((
+                event.channel.unique_id.clone(),
+                event.channel.channel.clone(),
+            ));
+        }
+
+        let started = if channels.len() > 1 {
+            let segment = TalkSegment {
+                bridge: id.clone(),
+                channels: channels.clone(),
+                started_at: Instant::now(),
+                ended_at: None,
+            };
+            let mut changed = None;
+            for linked_id in self.linked_ids(&channels) {
+                if let Some(call) = self.active.get_mut(&linked_id) {
+                    call.talk.push(segment.clone());
+                    changed = Some(linked_id);
+                }
+            }
+            changed
+        } else {
+            None
+        };
+
+        self.evict_bridge();
+        self.bridges.insert(id.clone(), channels);
+        let linked_id = self.channels.get(&event.channel.unique_id).cloned();
+        linked_id.or(started).or(ended)
+    }
+
+    fn end_segment(&mut self, bridge: &str, channels: &[(String, String)]) -> Option<String> {
+        let mut changed = None;
+        for linked_id in self.linked_ids(channels) {
+            let Some(call) = self.active.get_mut(&linked_id) else {
+                continue;
+            };
+            for segment in call
+                .talk
+                .iter_mut()
+                .filter(|x| x.bridge == bridge && x.ended_at.is_none())
+            {
+                segment.ended_at = Some(Instant::now());
+                changed = Some(linked_id.clone());
+            }
+        }
+        changed
+    }
+
+    /// Calls of the channels, without duplicates
+    fn linked_ids(&self, channels: &[(String, String)]) -> Vec<String> {
+        let mut linked_ids = Vec::new();
+        for (unique_id, _) in channels {
+            if let Some(linked_id) = self.channels.get(unique_id)
+                && !linked_ids.contains(linked_id)
+            {
+                linked_ids.push(linked_id.clone());
+            }
+        }
+        linked_ids
+    }
+
+    fn agent_complete(&mut self, agent: &AgentComplete) -> Option<String> {
+        let mut call = self
+            .agent_calls
+            .remove(&agent.caller_unique_id)
+            // The AgentConnect was before the connection with the AMI
+            .unwrap_or_else(|| agent.into());
+
+        call.end_hold();
+        call.completed_at = Some(Instant::now());
+        call.talk_time = Some(agent.talk_time);
+        call.reason = agent.reason.clone();
+
+        if self.completed_agent_calls.len() == FINISHED_CALLS {
+            self.completed_agent_calls.pop_front();
+        }
+        self.completed_agent_calls.push_back(call);
+        self.channels.get(&agent.caller_unique_id).cloned()
+    }
+
+    fn hold(&mut self, channel: &Channel, music: bool, start: bool) -> Option<String> {
+        let mut changed = false;
+        for call in self
+            .agent_calls
+            .values_mut()
+            .filter(|x| x.has_channel(&channel.unique_id))
+        {
+            call.hold(&channel.unique_id, music, start);
+            changed = true;
+        }
+        self.channels
+            .get(&channel.unique_id)
+            .cloned()
+            .filter(|_| changed)
+    }
+
+    /// The transfer event and AgentComplete (Reason: transfer) come in any order,
+    /// so the agent call can be already completed
+    fn transfer(&mut self, transferer: &Channel, transfer: Transfer) -> Option<String> {
+        let unique_id = &transferer.unique_id;
+        let call = match self.agent_calls.values_mut().find(|x| x.has_channel(unique_id)) {
+            Some(call) => call,
+            None => self
+                .completed_agent_calls
+                .iter_mut()
+                .rev()
+                .find(|x| x.has_channel(unique_id))?,
+        };
+        call.transfer = Some(transfer);
+        self.channels.get(&transferer.unique_id).cloned()
+    }
+
+    fn blind_transfer(&mut self, transfer: &BlindTransfer) -> Option<String> {
+        self.transfer(
+            &transfer.transferer,
+            Transfer {
+                kind: TransferKind::Blind {
+                    context: transfer.context.clone(),
+                    extension: transfer.extension.clone(),
+                },
+                result: transfer.result.clone(),
+                at: Instant::now(),
+            },
+        )
+    }
+
+    fn attended_transfer(&mut self, transfer: &AttendedTransfer) -> Option<String> {
+        self.transfer(
+            &transfer.orig_transferer,
+            Transfer {
+                kind: TransferKind::Attended {
+                    dest_type: transfer.dest_type.clone(),
+                },
+                result: transfer.result.clone(),
+                at: Instant::now(),
+            },
+        )
+    }
+
+    fn hangup(&mut self, hangup: &Hangup) -> Option<String> {
+        let linked_id = self
+            .channels
+            .get(&hangup.channel.unique_id)
+            .cloned()
+            .unwrap_or_else(|| hangup.channel.call_id().to_string());
+        let call = self.active.get_mut(&linked_id)?;
+        self.channels
+            .insert(hangup.channel.unique_id.clone(), linked_id.clone());
+
+        let channel = call.channel_mut(&hangup.channel);
+        channel.hangup = Some((hangup.cause, hangup.cause_txt.clone()));
+        channel.hungup_at = Some(Instant::now());
+
+        if call.channels.iter().all(|x| x.hungup_at.is_some()) {
+            let mut call = self.active.remove(&linked_id)?;
+            let now = Instant::now();
+            call.ended_at = Some(now);
+            for segment in call.talk.iter_mut().filter(|x| x.ended_at.is_none()) {
+                segment.ended_at = Some(now);
+            }
+            for channel in &call.channels {
+                self.channels.remove(&channel.unique_id);
+            }
+            if self.finished.len() == FINISHED_CALLS {
+                self.finished.pop_front();
+            }
+            self.finished.push_back(call);
+        }
+
+        Some(linked_id)
+    }
+}
+
+impl From<&Dial> for DialAttempt {
+    fn from(value: &Dial) -> Self {
+        Self {
+            caller: value.caller.as_ref().map(|x| x.unique_id.clone()),
+            dest: value.dest.channel.clone(),
+            dest_unique_id: value.dest.unique_id.clone(),
+            started_at: Instant::now(),
+            ended_at: None,
+            status: None,
+        }
+    }
+}
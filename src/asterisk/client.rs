@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot};
+
+use crate::asterisk::{
+    action::{Action, ActionResponse},
+    entities::Summary,
+    error::AmiError,
+    event::AmiMessage,
+    queue_action::QueueSummary,
+};
+
+/// Action waiting to be written by the [`EventHandler`](crate::asterisk::event::EventHandler)
+#[derive(Debug)]
+pub struct ClientRequest {
+    pub action: Action,
+    pub tx: oneshot::Sender<ActionResponse>,
+}
+
+pub type ClientReceiver = mpsc::UnboundedReceiver<ClientRequest>;
+
+/// Handle to send actions over the connection that receives the events.
+/// It can be cloned and used from other tasks while the event stream is running
+#[derive(Debug, Clone)]
+pub struct AmiClient {
+    tx: mpsc::UnboundedSender<ClientRequest>,
+    timeout: Duration,
+}
+
+impl AmiClient {
+    pub fn channel() -> (AmiClient, ClientReceiver) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let client = AmiClient {
+            tx,
+            timeout: Duration::from_secs(10),
+        };
+        (client, rx)
+    }
+
+    /// Max time waiting for the response of an action
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Send the action and wait for its response.
+    /// The actions are written once the login was accepted.
+    /// Fails if the handler was dropped, the connection was lost before the response
+    /// or the response didn't arrive in time.
+    /// An action with a line break in a header isn't sent, see [`Action::validate`]
+    pub async fn send_action(&self, action: impl Into<Action>) -> Result<ActionResponse, AmiError> {
+        let action = action.into();
+        action.validate()?;
+        let name = action.name.clone();
+        let (tx, rx) = oneshot::channel();
+        self.tx
+            .send(ClientRequest { action, tx })
+            .map_err(|_| AmiError::Disconnected)?;
+
+        match tokio::time::timeout(self.timeout, rx).await {
+            Ok(response) => response.map_err(|_| AmiError::Disconnected),
+            Err(_) => Err(AmiError::Timeout(name)),
+        }
+    }
+
+    /// Action: QueueSummary, the numbers of every queue or only of `queue`
+    pub async fn queue_summary(&self, queue: Option<&str>) -> Result<Vec<Summary>, AmiError> {
+        let mut action = QueueSummary::new();
+        if let Some(queue) = queue {
+            action = action.queue(queue);
+        }
+
+        let response = self.send_action(action).await?;
+        if !response.response.is_ok() {
+            return Err(AmiError::ActionFailed {
+                action: "QueueSummary".to_string(),
+                message: response.response.message,
+            });
+        }
+
+        Ok(response
+            .events
+            .into_iter()
+            .filter_map(|event| match event {
+                AmiMessage::Summary(summary) => Some(summary),
+                _ => None,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+/// State of the connection with the AMI server.
+/// It is sent to the consumers so they can show if the data is stale
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionState {
+    /// Trying to open the socket
+    Connecting,
+
+    /// The socket is open, the login sequence starts
+    Connected,
+
+    /// The server closed the connection or the socket failed
+    Disconnected,
+
+    /// Waiting `delay` before the attempt number `attempt`
+    Reconnecting { attempt: u32, delay: Duration },
+}
+
+impl std::fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionState::Connecting => write!(f, "Connecting"),
+            ConnectionState::Connected => write!(f, "Connected"),
+            ConnectionState::Disconnected => write!(f, "Disconnected"),
+            ConnectionState::Reconnecting { attempt, delay } => {
+                write!(f, "Reconnecting (attempt {attempt}, in {delay:?})")
+            }
+        }
+    }
+}
+
+/// Exponential backoff used between reconnection attempts
+///
+/// initial: delay of the first attempt
+/// max: upper bound of the delay
+/// multiplier: factor applied to the delay after each failed attempt, at least 1.0
+/// jitter: fraction of the delay (0.0 - 1.0) that is randomly subtracted.
+/// Both are set with the builders, that keep them in range
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    multiplier: f64,
+    jitter: f64,
+    attempt: u32,
+    saturated: bool,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(500), Duration::from_secs(30))
+    }
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            multiplier: 2.0,
+            jitter: 0.2,
+            attempt: 0,
+            saturated: false,
+        }
+    }
+
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Returns the delay to wait before the next attempt and increments the counter
+    pub fn next_delay(&mut self) -> Duration {
+        let exp = self.multiplier.powi(self.attempt.min(i32::MAX as u32) as i32);
+        let delay = if self.saturated {
+            self.max
+        } else {
+            self.initial.mul_f64(exp.min(u32::MAX as f64)).min(self.max)
+        };
+
+        self.attempt = self.attempt.saturating_add(1);
+
+        if self.jitter > 0.0 {
+            let factor = rand::rng().random_range(0.0..=self.jitter);
+            delay.mul_f64(1.0 - factor)
+        } else {
+            delay
+        }
+    }
+
+    /// Must be called when the login was accepted
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+        self.saturated = false;
+    }
+
+    /// The next delays are the max until a reset, used when retrying soon is useless
+    /// (e.g. the credentials were rejected)
+    pub fn saturate(&mut self) {
+        self.saturated = true;
+    }
+}
@@ -1,9 +1,9 @@
 use macros::ParserEvent;
 
 /// Raised when an queue member is notified of a caller in the queue.
-#[derive(Debug, ParserEvent)]
+#[derive(Debug, Clone, ParserEvent)]
 pub struct AgenteCalled {
-    #[parser(key = "Queue")]
+    #[parser(key = "Queue", required)]
     pub queue: String,
 
     #[parser(key = "MemberName")]
@@ -35,12 +35,16 @@ pub struct AgenteCalled {
 
     #[parser(key = "DestConnectedLineName")]
     pub dest_connected_line_name: String,
+
+    /// Channel variables, `ChanVariable(name): value`
+    #[parser(key = "ChanVariable", variables)]
+    pub chan_variables: Vec<(String, String)>,
 }
 
 /// Raised when a queue member answers and is bridged to a caller in the queue.
-#[derive(Debug, ParserEvent)]
+#[derive(Debug, Clone, ParserEvent)]
 pub struct AgentConnect {
-    #[parser(key = "Queue")]
+    #[parser(key = "Queue", required)]
     pub queue: String,
 
     #[parser(key = "Uniqueid")]
@@ -78,12 +82,16 @@ pub struct AgentConnect {
 
     #[parser(key = "HoldTime", use_parse)]
     pub hold_time: u64,
+
+    /// Channel variables, `ChanVariable(name): value`
+    #[parser(key = "ChanVariable", variables)]
+    pub chan_variables: Vec<(String, String)>,
 }
 
 // Raised when a queue member has finished servicing a caller in the queue.
-#[derive(Debug, ParserEvent)]
+#[derive(Debug, Clone, ParserEvent)]
 pub struct AgentComplete {
-    #[parser(key = "Queue")]
+    #[parser(key = "Queue", required)]
     pub queue: String,
 
     #[parser(key = "MemberName")]
@@ -124,12 +132,16 @@ pub struct AgentComplete {
 
     #[parser(key = "Reason")]
     pub reason: String,
+
+    /// Channel variables, `ChanVariable(name): value`
+    #[parser(key = "ChanVariable", variables)]
+    pub chan_variables: Vec<(String, String)>,
 }
 
 //Raised when a queue member is notified of a caller in the queue and fails to answer.
-#[derive(Debug, ParserEvent)]
+#[derive(Debug, Clone, ParserEvent)]
 pub struct AgentRingNoAnswer {
-    #[parser(key = "Queue")]
+    #[parser(key = "Queue", required)]
     pub queue: String,
 
     #[parser(key = "MemberName")]
@@ -164,12 +176,16 @@ pub struct AgentRingNoAnswer {
 
     #[parser(key = "DestUniqueid")]
     pub dest_unique_id: String,
+
+    /// Channel variables, `ChanVariable(name): value`
+    #[parser(key = "ChanVariable", variables)]
+    pub chan_variables: Vec<(String, String)>,
 }
 
 // Raised when a queue member hangs up on a caller in the queue.
-#[derive(Debug, ParserEvent)]
+#[derive(Debug, Clone, ParserEvent)]
 pub struct AgentDump {
-    #[parser(key = "Queue")]
+    #[parser(key = "Queue", required)]
     pub queue: String,
 
     #[parser(key = "MemberName")]
@@ -197,12 +213,61 @@ pub struct AgentDump {
     pub dest_unique_id: String,
 }
 
-// Raised when an Agent has logged in.
-#[derive(Debug)]
+/// Raised when an Agent has logged in (app_agent_pool, chan_agent in older versions).
+///
+/// Agent: agent id, as in agents.conf
+/// Channel: channel of the agent
+/// Linkedid: id of the oldest channel of the call
+#[derive(Debug, Clone, ParserEvent)]
 pub struct AgentLogin {
-    // ...
+    #[parser(key = "Agent", required)]
+    pub agent: String,
+
+    #[parser(key = "Channel")]
+    pub channel: String,
+
+    #[parser(key = "ChannelStateDesc")]
+    pub channel_state: String,
+
+    #[parser(key = "CallerIDNum")]
+    pub caller_id_num: String,
+
+    #[parser(key = "CallerIDName")]
+    pub caller_id_name: String,
+
+    #[parser(key = "Uniqueid")]
+    pub unique_id: String,
+
+    #[parser(key = "Linkedid")]
+    pub linked_id: String,
 }
 
-// Raised when an Agent has logged off.
-#[derive(Debug)]
-pub struct AgentLogoff;
+/// Raised when an Agent has logged off.
+///
+/// Logintime: seconds the agent was logged in
+#[derive(Debug, Clone, ParserEvent)]
+pub struct AgentLogoff {
+    #[parser(key = "Agent", required)]
+    pub agent: String,
+
+    #[parser(key = "Channel")]
+    pub channel: String,
+
+    #[parser(key = "ChannelStateDesc")]
+    pub channel_state: String,
+
+    #[parser(key = "CallerIDNum")]
+    pub caller_id_num: String,
+
+    #[parser(key = "CallerIDName")]
+    pub caller_id_name: String,
+
+    #[parser(key = "Uniqueid")]
+    pub unique_id: String,
+
+    #[parser(key = "Linkedid")]
+    pub linked_id: String,
+
+    #[parser(key = "Logintime", use_parse)]
+    pub log_in_time: u64,
+}
@@ -0,0 +1,44 @@
+use macros::ParserEvent;
+
+use crate::asterisk::entities::channel::Channel;
+
+/// Snapshot of a bridge, BridgeCreate and BridgeDestroy.
+///
+/// BridgeType: basic, holding...
+/// BridgeTechnology: simple_bridge, native_rtp, softmix...
+/// BridgeNumChannels: channels in the bridge when the event was raised
+#[derive(Debug, Clone, ParserEvent)]
+pub struct Bridge {
+    #[parser(key = "BridgeUniqueid", required)]
+    pub unique_id: String,
+
+    #[parser(key = "BridgeType")]
+    pub r#type: String,
+
+    #[parser(key = "BridgeTechnology")]
+    pub technology: String,
+
+    #[parser(key = "BridgeCreator")]
+    pub creator: String,
+
+    #[parser(key = "BridgeName")]
+    pub name: String,
+
+    #[parser(key = "BridgeNumChannels", use_parse)]
+    pub num_channels: u32,
+}
+
+/// BridgeEnter and BridgeLeave, a channel enters or leaves a bridge.
+///
+/// SwapUniqueid: only in BridgeEnter, the channel that this one replaces (e.g. in a transfer)
+#[derive(Debug, Clone, ParserEvent)]
+pub struct BridgeChannel {
+    #[parser(flatten)]
+    pub bridge: Bridge,
+
+    #[parser(flatten)]
+    pub channel: Channel,
+
+    #[parser(key = "SwapUniqueid")]
+    pub swap_unique_id: String,
+}
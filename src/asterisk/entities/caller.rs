@@ -1,17 +1,17 @@
 use macros::ParserEvent;
 
 /// Queue: Queue name
-/// Position: Queue position
+/// Position: Queue position, required: the callers are ordered by it
 /// CallerIDNum: Caller number
 /// CallerIDName: Caller name
 /// WaitTime: Time spent waiting
 /// Uniqueid
-#[derive(Debug, ParserEvent)]
+#[derive(Debug, Clone, ParserEvent)]
 pub struct Caller {
-    #[parser(key = "Queue")]
+    #[parser(key = "Queue", required)]
     pub queue: String,
 
-    #[parser(key = "Position", use_parse)]
+    #[parser(key = "Position", use_parse, required)]
     pub position: u16,
 
     #[parser(key = "CallerIDNum")]
@@ -26,6 +26,10 @@ pub struct Caller {
     #[parser(key = "HoldTime")]
     pub hold_time: String,
 
+    /// Channel variables, `ChanVariable(name): value`
+    #[parser(key = "ChanVariable", variables)]
+    pub chan_variables: Vec<(String, String)>,
+
     #[skip_with_defaut]
     pub r#type: TypeCallerEvent,
 }
@@ -37,7 +41,7 @@ impl Caller {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub enum TypeCallerEvent {
     /// the time field represent the time spend wating
     Join,
@@ -0,0 +1,97 @@
+use macros::ParserEvent;
+
+/// Snapshot of a channel, the headers shared by the channel events.
+/// The events with two channels use a prefix for the second one (e.g. `DestChannel`)
+///
+/// ChannelState: state number, ChannelStateDesc: Down, Ring, Ringing, Up, Busy...
+/// Uniqueid: id of the channel
+/// Linkedid: id of the oldest channel of the call, all the channels of a call share it
+#[derive(Debug, Clone, ParserEvent)]
+pub struct Channel {
+    #[parser(key = "Channel", required)]
+    pub channel: String,
+
+    #[parser(key = "ChannelState", use_parse)]
+    pub state: u8,
+
+    #[parser(key = "ChannelStateDesc")]
+    pub state_desc: String,
+
+    #[parser(key = "CallerIDNum")]
+    pub caller_id_num: String,
+
+    #[parser(key = "CallerIDName")]
+    pub caller_id_name: String,
+
+    #[parser(key = "ConnectedLineNum")]
+    pub connected_line_num: String,
+
+    #[parser(key = "ConnectedLineName")]
+    pub connected_line_name: String,
+
+    #[parser(key = "AccountCode")]
+    pub account_code: String,
+
+    #[parser(key = "Context")]
+    pub context: String,
+
+    #[parser(key = "Exten")]
+    pub exten: String,
+
+    #[parser(key = "Priority", use_parse)]
+    pub priority: u32,
+
+    #[parser(key = "Uniqueid", required)]
+    pub unique_id: String,
+
+    #[parser(key = "Linkedid")]
+    pub linked_id: String,
+}
+
+impl Channel {
+    /// Linkedid, the Uniqueid in the versions without it
+    pub fn call_id(&self) -> &str {
+        if self.linked_id.is_empty() {
+            &self.unique_id
+        } else {
+            &self.linked_id
+        }
+    }
+}
+
+/// Raised when a channel is hung up.
+///
+/// Cause: Q.850 cause code (16 normal clearing, 17 busy, 19 no answer...)
+/// Cause-txt: description of the cause
+#[derive(Debug, Clone, ParserEvent)]
+pub struct Hangup {
+    #[parser(flatten)]
+    pub channel: Channel,
+
+    #[parser(key = "Cause", use_parse)]
+    pub cause: u16,
+
+    #[parser(key = "Cause-txt")]
+    pub cause_txt: String,
+}
+
+/// DialBegin and DialEnd, a channel dials another one.
+///
+/// caller: None when the dial doesn't come from a channel (e.g. Originate)
+/// dest: dialed channel, the headers with the prefix Dest
+/// DialString: what was dialed
+/// DialStatus: only in DialEnd, ANSWER, BUSY, NOANSWER, CANCEL, CONGESTION, CHANUNAVAIL...
+#[derive(Debug, Clone, ParserEvent)]
+pub struct Dial {
+    #[parser(flatten)]
+    pub caller: Option<Channel>,
+
+    #[parser(flatten, prefix = "Dest")]
+    pub dest: Channel,
+
+    #[parser(key = "DialString")]
+    pub dial_string: String,
+
+    #[parser(key = "DialStatus")]
+    pub dial_status: String,
+}
@@ -1,17 +1,21 @@
 use macros::ParserEvent;
 
+use crate::asterisk::entities::parse_bool;
+
 ///
 /// Queue user information
 /// This information is received when we are connected to the queue event
 ///
 /// Queue: queue name
+/// Interface: Location in QueueMember, the one the actions need.
+/// The StateInterface (e.g. hint:100@ext-local for Local/100@from-queue/n) only when neither is sent
 ///
-#[derive(Debug, ParserEvent)]
+#[derive(Debug, Clone, ParserEvent)]
 pub struct Member {
-    #[parser(key = "Queue")]
+    #[parser(key = "Queue", required)]
     pub queue: String,
 
-    #[parser(key = "Interface", key = "StateInterface")]
+    #[parser(key = "Interface", key = "Location", key = "StateInterface", required)]
     pub interface: String,
 
     #[parser(key = "MemberName", key ="Name")]
@@ -30,23 +34,23 @@ pub struct Member {
     pub last_pause: u64,
 
     #[parser(key = "CallsTaken", use_parse)]
-    pub calls_taken: u16,
+    pub calls_taken: u32,
 
-    #[parser(key = "InCall", use_parse)]
+    #[parser(key = "InCall", with = "parse_bool")]
     pub in_call: bool,
 
-    #[parser(key = "Ringinuse", use_parse)]
+    #[parser(key = "Ringinuse", with = "parse_bool")]
     pub ring_in_use: bool,
 
     #[parser(key = "PausedReason")]
     pub pause_reason: String,
 
-    #[parser(key = "Paused", use_parse)]
+    #[parser(key = "Paused", with = "parse_bool")]
     pub paused: bool,
 }
 
 // Member status
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub enum Status {
     #[default]
     Unknown,
@@ -85,9 +89,9 @@ impl From<&str> for Status {
 /// CallerIDNum:
 /// CallerIDName:
 /// Uniqueid:
-#[derive(Debug, ParserEvent)]
+#[derive(Debug, Clone, ParserEvent)]
 pub struct MemberRingninuse {
-    #[parser(key = "Queue")]
+    #[parser(key = "Queue", required)]
     pub queue: String,
 
     #[parser(key = "Interface")]
@@ -1,19 +1,41 @@
 use macros::ParserEvent;
 
 pub mod agent;
+pub mod bridge;
 pub mod caller;
+pub mod channel;
 pub mod member;
+pub mod transfer;
+
+/// AMI sends the booleans as 0/1 or yes/no
+pub fn parse_bool(value: &str) -> bool {
+    matches!(
+        value.to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
 
 /// This struct represent one queue and its state
 /// Event: QueueParams
 ///
 /// Queue: queue name
 /// calls: active calls
-#[derive(Debug, ParserEvent)]
+/// Max: max callers waiting, 0 without limit
+/// ServiceLevel: seconds to answer a call within the service level
+/// ServicelevelPerf: % of the completed calls answered within the service level
+/// ServicelevelPerf2: the same % counting also the abandoned calls
+/// Weight: priority of the queue when a member is in several queues
+#[derive(Debug, Clone, Default, ParserEvent)]
 pub struct Params {
-    #[parser(key = "Queue")]
+    #[parser(key = "Queue", required)]
     pub queue: String,
 
+    #[parser(use_parse, key = "Max")]
+    pub max: u32,
+
+    #[parser(key = "Strategy")]
+    pub strategy: Strategy,
+
     #[parser(use_parse, key = "Calls")]
     pub calls: u32, // llamadas en cola
 
@@ -28,12 +50,85 @@ pub struct Params {
 
     #[parser(use_parse, key = "Abandoned")]
     pub abandoned: u32, // llamadas abandonadasq
+
+    #[parser(use_parse, key = "ServiceLevel")]
+    pub service_level: u32,
+
+    #[parser(use_parse, key = "ServicelevelPerf")]
+    pub service_level_perf: f64,
+
+    #[parser(use_parse, key = "ServicelevelPerf2")]
+    pub service_level_perf2: f64,
+
+    #[parser(use_parse, key = "Weight")]
+    pub weight: u32,
+}
+
+// How the queue rings the members
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strategy {
+    #[default]
+    Unknown,
+    RingAll,
+    LeastRecent,
+    FewestCalls,
+    Random,
+    RrMemory,
+    Linear,
+    WRandom,
+    RrOrdered,
+}
+
+impl From<&str> for Strategy {
+    fn from(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "ringall" => Self::RingAll,
+            "leastrecent" => Self::LeastRecent,
+            "fewestcalls" => Self::FewestCalls,
+            "random" => Self::Random,
+            "rrmemory" => Self::RrMemory,
+            "linear" => Self::Linear,
+            "wrandom" => Self::WRandom,
+            "rrordered" => Self::RrOrdered,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Live numbers of a queue, event QueueSummary
+///
+/// LoggedIn: members logged in, Available: members not paused and not in use
+/// Callers: callers waiting
+/// HoldTime, TalkTime: averages in seconds
+/// LongestHoldTime: wait of the oldest caller, in seconds
+#[derive(Debug, Clone, Default, ParserEvent)]
+pub struct Summary {
+    #[parser(key = "Queue", required)]
+    pub queue: String,
+
+    #[parser(key = "LoggedIn", use_parse)]
+    pub logged_in: u32,
+
+    #[parser(key = "Available", use_parse)]
+    pub available: u32,
+
+    #[parser(key = "Callers", use_parse)]
+    pub callers: u32,
+
+    #[parser(key = "HoldTime", use_parse)]
+    pub hold_time: u64,
+
+    #[parser(key = "TalkTime", use_parse)]
+    pub talk_time: u64,
+
+    #[parser(key = "LongestHoldTime", use_parse)]
+    pub longest_hold_time: u64,
 }
 
 // Caller in queue
-#[derive(Debug, ParserEvent)]
+#[derive(Debug, Clone, ParserEvent)]
 pub struct Entry {
-    #[parser(key = "Queue")]
+    #[parser(key = "Queue", required)]
     pub queue: String,
 
     #[parser(key = "CallerIDNum")]
@@ -48,6 +143,9 @@ pub struct Entry {
     #[parser(key = "ConnectedLineName")]
     pub connected_line_name: String,
 
+    #[parser(key = "Position", use_parse, required)]
+    pub position: u16,
+
     #[parser(key = "Wait", use_parse)]
     pub wait: u64,
 
@@ -55,20 +153,28 @@ pub struct Entry {
     pub unique_id: String,
 }
 
-#[derive(Debug, ParserEvent)]
+/// ListItems is required, it tells if the snapshot is complete
+#[derive(Debug, Clone, ParserEvent)]
 pub struct StatusComplete {
-    #[parser(key = "ListItems", use_parse)]
+    #[parser(key = "ListItems", use_parse, required)]
     pub len: i32,
 }
 
-#[derive(Debug, ParserEvent)]
+#[derive(Debug, Clone, ParserEvent)]
 pub struct ResponseAmi {
-    #[parser(key = "Response")]
+    #[parser(key = "Response", required)]
     pub response: ResponseAmiResult,
 
     #[parser(key = "Message", key = "Events")]
     pub message: String,
 
+    #[parser(key = "ActionID")]
+    pub action_id: String,
+
+    /// Only in the response to Action: Challenge
+    #[parser(key = "Challenge")]
+    pub challenge: String,
+
     #[skip_with_defaut]
     pub r#type: ResponseAmyType,
 }
@@ -80,21 +186,25 @@ impl ResponseAmi {
     }
 }
 
-#[derive(Debug, Default)]
+/// Action that generated the response, known by its ActionID
+#[derive(Debug, Clone, Default, PartialEq)]
 pub enum ResponseAmyType {
+    Challenge,
     Login,
+    Events,
+    QueueStatus,
     Action,
     #[default]
     Unknown
 }
 
 impl ResponseAmi {
-    fn is_ok(&self) -> bool {
+    pub fn is_ok(&self) -> bool {
         self.response == ResponseAmiResult::Success
     }
 }
 
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum ResponseAmiResult {
     Error,
     Success,
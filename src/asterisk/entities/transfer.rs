@@ -0,0 +1,75 @@
+use macros::ParserEvent;
+
+use crate::asterisk::entities::{channel::Channel, parse_bool};
+
+/// Raised when a blind transfer is complete.
+///
+/// Result: Success, Fail, Invalid or Not Permitted
+/// transferer: channel that requested the transfer, headers with the prefix Transferer
+/// transferee: channel being transferred, headers with the prefix Transferee
+/// Context, Extension: destination of the transfer
+#[derive(Debug, Clone, ParserEvent)]
+pub struct BlindTransfer {
+    #[parser(key = "Result")]
+    pub result: String,
+
+    #[parser(flatten, prefix = "Transferer")]
+    pub transferer: Channel,
+
+    #[parser(flatten, prefix = "Transferee")]
+    pub transferee: Option<Channel>,
+
+    #[parser(key = "IsExternal", with = "parse_bool")]
+    pub is_external: bool,
+
+    #[parser(key = "Context")]
+    pub context: String,
+
+    #[parser(key = "Extension")]
+    pub extension: String,
+}
+
+/// Raised when an attended transfer is complete.
+///
+/// orig_transferer: channel that requested the transfer, prefix OrigTransferer
+/// second_transferer: channel of the consultation call, prefix SecondTransferer
+/// transferee, transfer_target: the channels that end up together, prefix Transferee and TransferTarget
+/// DestType: Bridge, App, Link, Threeway or Fail
+#[derive(Debug, Clone, ParserEvent)]
+pub struct AttendedTransfer {
+    #[parser(key = "Result")]
+    pub result: String,
+
+    #[parser(flatten, prefix = "OrigTransferer")]
+    pub orig_transferer: Channel,
+
+    #[parser(flatten, prefix = "SecondTransferer")]
+    pub second_transferer: Option<Channel>,
+
+    #[parser(flatten, prefix = "Transferee")]
+    pub transferee: Option<Channel>,
+
+    #[parser(flatten, prefix = "TransferTarget")]
+    pub transfer_target: Option<Channel>,
+
+    #[parser(key = "DestType")]
+    pub dest_type: String,
+
+    #[parser(key = "DestBridgeUniqueid")]
+    pub dest_bridge: String,
+
+    #[parser(key = "DestApp")]
+    pub dest_app: String,
+}
+
+/// Hold, Unhold, MusicOnHoldStart and MusicOnHoldStop of a channel.
+///
+/// MusicClass (Hold) or Class (MusicOnHoldStart): class of the music, empty in the stop events
+#[derive(Debug, Clone, ParserEvent)]
+pub struct Hold {
+    #[parser(flatten)]
+    pub channel: Channel,
+
+    #[parser(key = "MusicClass", key = "Class")]
+    pub music_class: String,
+}
@@ -0,0 +1,95 @@
+/// Errors of the connection with the AMI and of the parser
+#[derive(Debug)]
+pub enum AmiError {
+    /// The socket failed
+    Io(std::io::Error),
+
+    /// Invalid TLS configuration or certificate
+    Tls(String),
+
+    /// Response: Error to Action: Login, it contains the Message
+    AuthenticationRejected(String),
+
+    /// A frame without Response or Event
+    MalformedFrame(String),
+
+    /// The frame is not valid UTF-8, it contains the frame decoded with the charset of the connection.
+    /// The message is parsed anyway
+    InvalidUtf8 {
+        error: std::str::Utf8Error,
+        frame: String,
+    },
+
+    /// A frame without blank line longer than the limit, the data until the next blank line is discarded
+    FrameTooLarge { limit: usize },
+
+    /// A required key is not in the frame
+    MissingField(String),
+
+    /// The value of the key can not be parsed
+    FieldParse { key: String, value: String },
+
+    /// Response: Error to an action, it contains the Message
+    ActionFailed { action: String, message: String },
+
+    /// A header of the action contains CR or LF, it would end the frame and inject other actions.
+    /// The action isn't sent
+    InvalidHeader { key: String, value: String },
+
+    /// The server didn't answer in time: an action, the Login sequence or the connection
+    Timeout(String),
+
+    /// The connection was closed before the response
+    Disconnected,
+
+    /// The QueueStatus event list lost events (ListItems doesn't match), the state wasn't updated.
+    /// expected: ListItems, None if QueueStatusComplete wasn't received
+    IncompleteSnapshot { expected: Option<i32>, received: usize },
+
+    /// The capture file can't be written, the capture stops and the connection keeps working
+    Capture(std::io::Error),
+}
+
+impl std::fmt::Display for AmiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AmiError::Io(er) => write!(f, "I/O error: {er}"),
+            AmiError::Tls(er) => write!(f, "TLS error: {er}"),
+            AmiError::AuthenticationRejected(msg) => write!(f, "Authentication rejected: {msg}"),
+            AmiError::MalformedFrame(frame) => write!(f, "Malformed frame: {frame:?}"),
+            AmiError::InvalidUtf8 { error, frame } => write!(f, "Invalid UTF-8 ({error}): {frame:?}"),
+            AmiError::FrameTooLarge { limit } => {
+                write!(f, "Frame larger than {limit} bytes, skipped until the next blank line")
+            }
+            AmiError::MissingField(key) => write!(f, "Missing field {key}"),
+            AmiError::FieldParse { key, value } => write!(f, "Invalid value of {key}: {value:?}"),
+            AmiError::ActionFailed { action, message } => write!(f, "{action} failed: {message}"),
+            AmiError::InvalidHeader { key, value } => {
+                write!(f, "Line break in the header {key:?}: {value:?}")
+            }
+            AmiError::Timeout(action) => write!(f, "Timeout waiting for {action}"),
+            AmiError::Disconnected => write!(f, "Disconnected"),
+            AmiError::Capture(er) => write!(f, "Capture stopped: {er}"),
+            AmiError::IncompleteSnapshot { expected, received } => match expected {
+                Some(expected) => write!(f, "Incomplete QueueStatus: {received} of {expected} events"),
+                None => write!(f, "Incomplete QueueStatus: {received} events without QueueStatusComplete"),
+            },
+        }
+    }
+}
+
+impl std::error::Error for AmiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AmiError::Io(er) | AmiError::Capture(er) => Some(er),
+            AmiError::InvalidUtf8 { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for AmiError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
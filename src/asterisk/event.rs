@@ -1,63 +1,340 @@
-use std::{collections::HashMap, pin::Pin, task::Poll};
+use std::{borrow::Cow, pin::Pin, task::Poll, time::Duration};
 
-use futures::FutureExt;
+use bytes::{BufMut, Bytes, BytesMut};
 use tokio::{
-    io::{AsyncReadExt, AsyncWrite},
+    io::{AsyncRead, AsyncWrite, ReadHalf, WriteHalf},
     net::{
         TcpStream,
         tcp::{OwnedReadHalf, OwnedWriteHalf},
     },
+    sync::oneshot::{self, error::TryRecvError},
+    time::{Instant, Interval, MissedTickBehavior, Sleep},
 };
+use tokio_util::{codec::Decoder, io::poll_read_buf};
 
-use crate::{asterisk::entities::{
-    Entry, Params, ResponseAmi, StatusComplete,
-    agent::{AgentComplete, AgentConnect, AgentDump, AgentRingNoAnswer, AgenteCalled},
-    caller::{Caller, TypeCallerEvent},
-    member::*,
-}, io::writer::BufWriter};
-
-pub struct EventHandler {
-    reader: OwnedReadHalf,
-    writer: BufWriter<OwnedWriteHalf>,
-    buffer: Vec<u8>,
-    processed: usize,
+use crate::{asterisk::{
+    action::{ActionResponse, EventList, FollowUp, PendingActions},
+    client::{AmiClient, ClientReceiver},
+    error::AmiError,
+    headers::Headers,
+    entities::{
+        Entry, Params, ResponseAmi, ResponseAmyType, StatusComplete, Summary,
+        agent::{
+            AgentComplete, AgentConnect, AgentDump, AgentLogin, AgentLogoff, AgentRingNoAnswer,
+            AgenteCalled,
+        },
+        bridge::{Bridge, BridgeChannel},
+        caller::{Caller, TypeCallerEvent},
+        channel::{Channel, Dial, Hangup},
+        member::*,
+        transfer::{AttendedTransfer, BlindTransfer, Hold},
+    },
+    state::QueueSnapshot,
+    version::{AmiVersion, Greeting},
+}, io::{codec::AmiCodec, writer::BufWriter}};
+
+/// How the credentials are sent in Action: Login
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LoginMode {
+    /// The secret is sent in clear text
+    #[default]
+    Plain,
+
+    /// Action: Challenge with AuthType: MD5, the login sends md5(challenge + secret)
+    Md5,
+}
+
+/// How the frames that aren't valid UTF-8 are decoded, e.g. caller names from old trunks.
+/// The valid UTF-8 frames are always read as UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Charset {
+    /// The invalid bytes are replaced with U+FFFD
+    #[default]
+    Utf8Lossy,
+
+    /// ISO-8859-1, each byte is a char
+    Latin1,
+}
+
+impl Charset {
+    pub fn decode<'a>(&self, data: &'a [u8]) -> Cow<'a, str> {
+        match self {
+            Charset::Utf8Lossy => String::from_utf8_lossy(data),
+            Charset::Latin1 => match std::str::from_utf8(data) {
+                Ok(data) => Cow::Borrowed(data),
+                Err(_) => Cow::Owned(data.iter().map(|x| *x as char).collect()),
+            },
+        }
+    }
+}
+
+/// Default limit of a frame, the QueueStatus events are a few hundred bytes
+pub const MAX_FRAME_SIZE: usize = 64 * 1024;
+
+/// Default limit of the read buffer
+pub const MAX_BUFFERED: usize = 1024 * 1024;
+
+/// Default time for the login, from the connection to the response of Action: Events
+pub const LOGIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Max bytes read from the socket at once
+const READ_SIZE: usize = 8 * 1024;
+
+/// AMI connection over any split transport (TCP, TLS, ...)
+pub struct EventHandler<R = OwnedReadHalf, W = OwnedWriteHalf> {
+    reader: R,
+    writer: BufWriter<W>,
+    buffer: BytesMut,
+    codec: AmiCodec,
     state: State,
     username: String,
     secret: String,
+    pending: PendingActions,
+    client: AmiClient,
+    commands: ClientReceiver,
+    logged_in: bool,
+    /// ActionID of the QueueStatus waiting for its event list
+    snapshot: Option<(String, oneshot::Receiver<ActionResponse>)>,
+    resync_period: Option<Duration>,
+    resync: Option<Interval>,
+    login_mode: LoginMode,
+    challenge: Option<String>,
+    greeted: bool,
+    version: Option<AmiVersion>,
+    max_frame_size: usize,
+    max_buffered: usize,
+    charset: Charset,
+    queued: Option<AmiError>,
+    call_events: bool,
+    login_timeout: Duration,
+    login_deadline: Option<Pin<Box<Sleep>>>,
 }
 
 impl EventHandler {
     pub fn new(stream: TcpStream, username: String, secret: String) -> Self {
         let (reader, writer) = stream.into_split();
+        Self::from_split(reader, writer, username, secret)
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite> EventHandler<ReadHalf<S>, WriteHalf<S>> {
+    /// Any bidirectional stream, e.g. `tokio::io::duplex` to drive the handler without a PBX
+    pub fn from_stream(stream: S, username: String, secret: String) -> Self {
+        let (reader, writer) = tokio::io::split(stream);
+        Self::from_split(reader, writer, username, secret)
+    }
+}
+
+impl<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> EventHandler<R, W> {
+    /// The reader can be a recorded byte stream (e.g. `&[u8]`) and the writer `tokio::io::sink()`
+    pub fn from_split(reader: R, writer: W, username: String, secret: String) -> Self {
+        let (client, commands) = AmiClient::channel();
         Self {
             reader,
             writer: BufWriter::new(writer),
-            buffer: Vec::new(),
-            processed: 0,
+            buffer: BytesMut::new(),
+            codec: AmiCodec::new(),
             state: State::State0Login,
             username,
             secret,
+            pending: PendingActions::default(),
+            client,
+            commands,
+            logged_in: false,
+            snapshot: None,
+            resync_period: None,
+            resync: None,
+            login_mode: LoginMode::Plain,
+            challenge: None,
+            greeted: false,
+            version: None,
+            max_frame_size: MAX_FRAME_SIZE,
+            max_buffered: MAX_BUFFERED,
+            charset: Charset::Utf8Lossy,
+            queued: None,
+            call_events: false,
+            login_timeout: LOGIN_TIMEOUT,
+            login_deadline: None,
         }
     }
 
+    pub fn login_mode(mut self, login_mode: LoginMode) -> Self {
+        self.login_mode = login_mode;
+        self
+    }
+
+    /// Subscribe to the class `call` too (Newchannel, Hangup, DialBegin...),
+    /// it's a lot of events in a busy PBX so it's disabled by default
+    pub fn call_events(mut self, enabled: bool) -> Self {
+        self.call_events = enabled;
+        self
+    }
+
+    /// Charset of the frames that aren't valid UTF-8
+    pub fn charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    /// A frame longer than `limit` is discarded with AmiError::FrameTooLarge,
+    /// the reading continues after the next blank line
+    pub fn max_frame_size(mut self, limit: usize) -> Self {
+        self.max_frame_size = limit.max(1);
+        self.codec = self.new_codec();
+        self
+    }
+
+    /// Max bytes kept in the read buffer, the frames can't be longer than this
+    pub fn max_buffered(mut self, limit: usize) -> Self {
+        self.max_buffered = limit.max(1);
+        self.codec = self.new_codec();
+        self
+    }
+
+    fn new_codec(&self) -> AmiCodec {
+        AmiCodec::new().max_frame_size(self.max_frame_size.min(self.max_buffered))
+    }
+
+    /// Max time for the login sequence (banner, Challenge, Login and Events).
+    /// When it expires the stream ends with AmiError::Timeout, e.g. a half-open connection
+    pub fn login_timeout(mut self, timeout: Duration) -> Self {
+        self.login_timeout = timeout;
+        self
+    }
+
+    /// Send Action: QueueStatus every `period` to correct the drift from missed events
+    pub fn resync(mut self, period: Duration) -> Self {
+        self.resync_period = Some(period);
+        self
+    }
+
+    /// Use a channel created outside the handler,
+    /// so the clients are available before the connection is opened
+    pub fn channel(mut self, client: AmiClient, commands: ClientReceiver) -> Self {
+        self.client = client;
+        self.commands = commands;
+        self
+    }
+
+    pub fn client(&self) -> AmiClient {
+        self.client.clone()
+    }
+
+    /// The login of the current connection was accepted
+    pub fn logged_in(&self) -> bool {
+        self.logged_in
+    }
+
+    /// Version of the AMI protocol announced in the banner of the current connection
+    pub fn version(&self) -> Option<AmiVersion> {
+        self.version
+    }
+
+    /// Replace the socket after a reconnection.
+    /// The pending data of the old socket is discarded and the login sequence starts again
+    pub fn reconnect(&mut self, reader: R, writer: W) {
+        self.reader = reader;
+        self.writer = BufWriter::new(writer);
+        self.buffer.clear();
+        self.codec.reset();
+        self.state = State::State0Login;
+        self.pending.clear();
+        self.logged_in = false;
+        self.snapshot = None;
+        self.resync = None;
+        self.challenge = None;
+        self.greeted = false;
+        self.version = None;
+        self.queued = None;
+        self.login_deadline = None;
+    }
+
     pub fn login(&self) -> String {
-        format!(
-            "Action: Login\r\nUsername: {}\r\nSecret: {}\r\n\r\n",
-            self.username, self.secret
-        )
+        match &self.challenge {
+            Some(challenge) => format!(
+                "Action: Login\r\nAuthType: MD5\r\nUsername: {}\r\nKey: {:x}\r\n",
+                self.username,
+                md5::compute(format!("{challenge}{}", self.secret))
+            ),
+            None => format!(
+                "Action: Login\r\nUsername: {}\r\nSecret: {}\r\n",
+                self.username, self.secret
+            ),
+        }
+    }
+
+    pub fn challenge(&self) -> &'static str {
+        "Action: Challenge\r\nAuthType: MD5\r\n"
+    }
+
+    pub fn event(&self) -> &'static str {
+        if self.call_events {
+            "Action: Events\r\nEventMask: queue,agent,call\r\n"
+        } else {
+            "Action: Events\r\nEventMask: queue,agent\r\n"
+        }
+    }
+
+    pub fn info_queue(&self) -> &'static str {
+        "Action: QueueStatus\r\n"
     }
 
-    pub fn event(&self) -> &'static [u8] {
-        b"Action: Events\r\nEventMask: queue,agent\r\n\r\n"
+    /// Send an action, `action` are the headers without the ActionID and the final blank line.
+    /// The receiver resolves when the response (and its event list, if any) arrives
+    pub fn action(&mut self, action: &str) -> oneshot::Receiver<ActionResponse> {
+        let (tx, rx) = oneshot::channel();
+        self.write_action(ResponseAmyType::Action, action, Some(tx), None);
+        rx
     }
 
-    pub fn info_queue(&self) -> &'static [u8] {
-        b"Action: QueueStatus\r\n\r\n"
+    /// Send Action: QueueStatus
+    fn request_snapshot(&mut self) {
+        let (tx, rx) = oneshot::channel();
+        let id = self.write_action(ResponseAmyType::QueueStatus, self.info_queue(), Some(tx), None);
+        self.snapshot = Some((id, rx));
+    }
+
+    /// Drop the QueueStatus waiting for its event list, e.g. QueueStatusComplete was lost
+    fn discard_snapshot(&mut self) -> Option<AmiError> {
+        let (id, _) = self.snapshot.take()?;
+        Some(AmiError::IncompleteSnapshot {
+            expected: None,
+            received: self.pending.cancel(&id).unwrap_or_default(),
+        })
+    }
+
+    /// Returns the snapshot when QueueStatusComplete was received
+    fn take_snapshot(&mut self) -> Option<QueueSnapshot> {
+        match self.snapshot.as_mut()?.1.try_recv() {
+            Ok(response) => {
+                self.snapshot = None;
+                Some(response.into())
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Closed) => {
+                self.snapshot = None;
+                None
+            }
+        }
+    }
+
+    fn write_action(
+        &mut self,
+        r#type: ResponseAmyType,
+        action: &str,
+        tx: Option<oneshot::Sender<ActionResponse>>,
+        follow_up: Option<FollowUp>,
+    ) -> String {
+        let id = self.pending.register(r#type, tx, follow_up);
+        self.writer.to_write(action.as_bytes());
+        self.writer
+            .to_write(format!("ActionID: {id}\r\n\r\n").as_bytes());
+        id
     }
 }
 
-impl futures::stream::Stream for EventHandler {
-    type Item = Result<AmiMessage, ()>;
+impl<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> futures::stream::Stream for EventHandler<R, W> {
+    type Item = Result<AmiMessage, AmiError>;
 
     fn poll_next(
         self: std::pin::Pin<&mut Self>,
@@ -65,89 +342,228 @@ impl futures::stream::Stream for EventHandler {
     ) -> std::task::Poll<Option<Self::Item>> {
         let this = self.get_mut();
         loop {
+            // Errors reported after the message of the same frame
+            if let Some(er) = this.queued.take() {
+                return Poll::Ready(Some(Err(er)));
+            }
+
+            if let Some(deadline) = this.login_deadline.as_mut()
+                && deadline.as_mut().poll(cx).is_ready()
+            {
+                this.login_deadline = None;
+                this.state = State::Done;
+                return Poll::Ready(Some(Err(AmiError::Timeout("Login".to_string()))));
+            }
+
             match this.state.clone() {
                 State::Write => {
                     let pin = Pin::new(&mut this.writer);
                     if let Err(er) = futures::ready!(pin.poll_flush(cx)) {
-                        println!("{}", er);
+                        this.state = State::Done;
+                        return Poll::Ready(Some(Err(er.into())));
                     }
 
-                    this.state = State::Read;
+                    this.state = if this.buffer.is_empty() {
+                        State::Read
+                    } else {
+                        State::CheckToProcess {
+                            check: InnerStateCheckToProcess::ToContinue,
+                        }
+                    };
                 }
                 State::State0Login => {
-                    this.writer.to_write(this.login().as_bytes());
+                    if this.login_deadline.is_none() {
+                        this.login_deadline = Some(Box::pin(tokio::time::sleep(this.login_timeout)));
+                    }
+                    if this.login_mode == LoginMode::Md5 && this.challenge.is_none() {
+                        this.write_action(ResponseAmyType::Challenge, this.challenge(), None, None);
+                    } else {
+                        let login = this.login();
+                        this.write_action(ResponseAmyType::Login, &login, None, None);
+                    }
                     this.state = State::Write;
                 }
                 State::State1Subscriber => {
-                    this.writer.to_write(this.event());
+                    this.write_action(ResponseAmyType::Events, this.event(), None, None);
                     this.state = State::Write;
                 }
                 State::State2Data => {
-                    this.writer.to_write(this.info_queue());
+                    this.request_snapshot();
+                    if let Some(period) = this.resync_period {
+                        let mut resync = tokio::time::interval_at(Instant::now() + period, period);
+                        resync.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                        this.resync = Some(resync);
+                    }
                     this.state = State::Write;
                 }
                 State::Done => return Poll::Ready(None),
                 State::CheckToProcess { check } => {
-                    if let Some(pos) = this.buffer[this.processed..]
-                        .windows(4)
-                        .position(|x| x == b"\r\n\r\n")
-                    {
-                        this.state = State::Process;
-                        this.processed += pos + 3;
-                    } else if !check.is_to_continue() {
-                        this.state = State::Done;
+                    let frame = if check.is_to_continue() {
+                        this.codec.decode(&mut this.buffer)
                     } else {
-                        this.state = State::Read;
-                        this.processed = this.buffer.len().saturating_sub(4);
+                        this.codec.decode_eof(&mut this.buffer)
+                    };
+
+                    match frame {
+                        Ok(Some(frame)) => this.state = State::Process { frame },
+                        Ok(None) if check.is_to_continue() => this.state = State::Read,
+                        Ok(None) => this.state = State::Done,
+                        // The codec discards the rest of the frame
+                        Err(er) => return Poll::Ready(Some(Err(er))),
                     }
                 }
                 State::Read => {
-                    let mut buf = [0u8; 1024];
-                    let n = match futures::ready!(this.reader.read(&mut buf).boxed().poll_unpin(cx))
+                    if let Some(resync) = this.resync.as_mut()
+                        && resync.poll_tick(cx).is_ready()
                     {
+                        // A whole period without QueueStatusComplete, it's not coming anymore
+                        let stale = this.discard_snapshot();
+                        this.request_snapshot();
+                        if let Some(er) = stale {
+                            return Poll::Ready(Some(Err(er)));
+                        }
+                    }
+
+                    if this.logged_in {
+                        while let Poll::Ready(Some(request)) = this.commands.poll_recv(cx) {
+                            // Dropping the sender fails the request, the frame would be split
+                            if request.action.validate().is_err() {
+                                continue;
+                            }
+                            let frame = request.action.to_frame();
+                            this.write_action(
+                                ResponseAmyType::Action,
+                                &frame,
+                                Some(request.tx),
+                                request.action.follow_up,
+                            );
+                        }
+                    }
+
+                    if !this.writer.is_empty() {
+                        this.state = State::Write;
+                        continue;
+                    }
+
+                    // Once it's full the codec discards the frame, so at least one byte is read
+                    let available = this
+                        .max_buffered
+                        .saturating_sub(this.buffer.len())
+                        .clamp(1, READ_SIZE);
+                    this.buffer.reserve(available);
+                    let mut buf = (&mut this.buffer).limit(available);
+                    let n = match futures::ready!(poll_read_buf(Pin::new(&mut this.reader), cx, &mut buf)) {
                         Ok(n) => n,
                         Err(er) => {
-                            println!("{er}");
-                            return Poll::Ready(None);
+                            this.state = State::Done;
+                            return Poll::Ready(Some(Err(er.into())));
                         }
                     };
-                    if n == 0 {
-                        this.state = State::Eof
+                    this.state = if n == 0 {
+                        State::Eof
                     } else {
-                        println!("bytes leidos {n}");
-                        this.buffer.extend_from_slice(&buf[..n]);
-                        this.state = State::CheckToProcess {
+                        State::CheckToProcess {
                             check: InnerStateCheckToProcess::ToContinue,
                         }
-                    }
+                    };
                 }
-                State::Process => {
-                    let data = std::str::from_utf8(&this.buffer[..=this.processed]).unwrap();
-                    let data = data.trim_end().to_string();
-                    println!("\n\n{data}");
-                    let tmp = AmiMessage::try_from(&data[..]);
-                    println!("\n{tmp:?}");
-                    this.buffer.drain(..=this.processed);
-                    this.processed = 0;
-                    if data.ends_with("Authentication accepted") {
-                        this.state = State::State1Subscriber;
-                        continue;
+                State::Process { frame } => {
+                    this.state = State::CheckToProcess {
+                        check: InnerStateCheckToProcess::ToContinue,
+                    };
+
+                    // The first frame without headers is the banner
+                    if !std::mem::replace(&mut this.greeted, true) && !frame.contains(&b':') {
+                        let greeting = Greeting::from(this.charset.decode(&frame).as_ref());
+                        this.version = greeting.version;
+                        return Poll::Ready(Some(Ok(AmiMessage::Greeting(greeting))));
                     }
-                    if data.ends_with("Events: On") {
-                        this.state = State::State2Data;
-                        continue;
+
+                    let data = match std::str::from_utf8(&frame) {
+                        Ok(data) => Cow::Borrowed(data),
+                        Err(error) => {
+                            let data = this.charset.decode(&frame);
+                            this.queued = Some(AmiError::InvalidUtf8 {
+                                error,
+                                frame: data.to_string(),
+                            });
+                            data
+                        }
+                    };
+                    let data = data.as_ref();
+
+                    let map = EventGenMap::gen_map(data);
+                    let list = EventList::from(map.get("EventList"));
+                    let action_id = map.get("ActionID").map(|x| x.to_string());
+
+                    let mut msg = match AmiMessage::try_from(map) {
+                        Ok(msg) => msg,
+                        Err(er) => {
+                            // The action can't be complete without this frame
+                            if let Some(id) = &action_id {
+                                if this.snapshot.as_ref().is_some_and(|(x, _)| x == id) {
+                                    let incomplete = this.discard_snapshot();
+                                    this.queued = this.queued.take().or(incomplete);
+                                } else {
+                                    this.pending.cancel(id);
+                                }
+                            }
+                            return Poll::Ready(Some(Err(match er {
+                                AmiError::MalformedFrame(_) => AmiError::MalformedFrame(data.to_string()),
+                                er => er,
+                            })));
+                        }
+                    };
+
+                    let Some(action_id) = action_id else {
+                        this.pending.follow_up(&msg);
+                        return Poll::Ready(Some(Ok(msg)));
+                    };
+
+                    if let AmiMessage::Response(response) = &mut msg {
+                        response.r#type = this.pending.r#type(&action_id);
+
+                        match response.r#type {
+                            ResponseAmyType::Login if response.is_ok() => {
+                                this.pending.dispatch(&action_id, msg, list);
+                                this.logged_in = true;
+                                this.state = State::State1Subscriber;
+                                continue;
+                            }
+                            ResponseAmyType::Challenge if response.is_ok() => {
+                                this.challenge = Some(std::mem::take(&mut response.challenge));
+                                this.pending.dispatch(&action_id, msg, list);
+                                this.state = State::State0Login;
+                                continue;
+                            }
+                            ResponseAmyType::Login | ResponseAmyType::Challenge => {
+                                // Nothing else is sent with a rejected login
+                                let message = std::mem::take(&mut response.message);
+                                this.pending.clear();
+                                this.state = State::Done;
+                                return Poll::Ready(Some(Err(AmiError::AuthenticationRejected(
+                                    message,
+                                ))));
+                            }
+                            ResponseAmyType::Events if response.is_ok() => {
+                                this.login_deadline = None;
+                                this.pending.dispatch(&action_id, msg, list);
+                                this.state = State::State2Data;
+                                continue;
+                            }
+                            _ => {}
+                        }
                     }
-                    if !this.buffer.is_empty() {
-                        this.state = State::CheckToProcess {
-                            check: InnerStateCheckToProcess::ToContinue,
-                        };
-                        return Poll::Ready(Some(tmp));
-                    } else {
-                        this.state = State::Read
+
+                    if let Some(msg) = this.pending.dispatch(&action_id, msg, list) {
+                        return Poll::Ready(Some(Ok(msg)));
+                    }
+
+                    if let Some(snapshot) = this.take_snapshot() {
+                        return Poll::Ready(Some(Ok(AmiMessage::Snapshot(snapshot))));
                     }
                 }
                 State::Eof => {
-                    println!("FIN");
                     this.state = State::CheckToProcess {
                         check: InnerStateCheckToProcess::ToFinish,
                     };
@@ -158,6 +574,7 @@ impl futures::stream::Stream for EventHandler {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 enum State {
     State0Login,
     State1Subscriber,
@@ -165,7 +582,7 @@ enum State {
     State2Data,
     Read,
     CheckToProcess { check: InnerStateCheckToProcess },
-    Process,
+    Process { frame: Bytes },
     Done,
     Eof,
 }
@@ -182,13 +599,21 @@ impl InnerStateCheckToProcess {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum AmiMessage {
+    /// Banner sent by the server when the connection is opened
+    Greeting(Greeting),
+
     Response(ResponseAmi),
 
+    /// Whole event list of Action: QueueStatus
+    Snapshot(QueueSnapshot),
+
     Params(Params),
     Entry(Entry),
     StatusComplete(StatusComplete),
+    Summary(Summary),
+    SummaryComplete(StatusComplete),
     CallerJoin(Caller),
     CallerLeave(Caller),
     CallerAbandon(Caller),
@@ -206,53 +631,114 @@ pub enum AmiMessage {
 
     AgentRingNoAnswer(AgentRingNoAnswer), // si AMI tiene campos asociados, hacer struct
     AgentDump(AgentDump),
-    None,
+    AgentLogin(AgentLogin),
+    AgentLogoff(AgentLogoff),
+
+    /// Channel events, only with [`EventHandler::call_events`]
+    Newchannel(Channel),
+    Newstate(Channel),
+    Hangup(Hangup),
+    DialBegin(Dial),
+    DialEnd(Dial),
+    BridgeCreate(Bridge),
+    BridgeEnter(BridgeChannel),
+    BridgeLeave(BridgeChannel),
+    BridgeDestroy(Bridge),
+    AttendedTransfer(Box<AttendedTransfer>),
+    BlindTransfer(BlindTransfer),
+    Hold(Hold),
+    Unhold(Hold),
+    MusicOnHoldStart(Hold),
+    MusicOnHoldStop(Hold),
+
+    /// Event not modeled by the crate, the headers (without Event) in the order they were received
+    Unknown {
+        event: String,
+        headers: Vec<(String, String)>,
+    },
 }
 
 impl TryFrom<&str> for AmiMessage {
-    type Error = ();
+    type Error = AmiError;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let mut map = EventGenMap::gen_map(value);
+        Self::try_from(EventGenMap::gen_map(value))
+    }
+}
 
+impl TryFrom<Headers<'_>> for AmiMessage {
+    type Error = AmiError;
+    fn try_from(map: Headers<'_>) -> Result<Self, Self::Error> {
         if map.contains_key("Response") {
-            if map.contains_key("Events") {
-                return Ok(AmiMessage::Response(ResponseAmi::parse_from_map(map).r#type(super::entities::ResponseAmyType::Action)));
-            } else {
-                return Ok(AmiMessage::Response(ResponseAmi::parse_from_map(map).r#type(super::entities::ResponseAmyType::Login)));
-            }
+            return Ok(AmiMessage::Response(ResponseAmi::parse_from_map(map)?));
         }
 
-        match map.remove("Event").unwrap_or_default() {
+        let Some(event) = map.get("Event") else {
+            return Err(AmiError::MalformedFrame(String::new()));
+        };
+
+        match event {
             "QueueStatusComplete" => Ok(AmiMessage::StatusComplete(
-                StatusComplete::parse_from_map(map),
+                StatusComplete::parse_from_map(map)?,
             )),
-            "QueueMemberStatus" => Ok(Self::MemberStatus(Member::parse_from_map(map))),
-            "QueueParams" => Ok(Self::Params(Params::parse_from_map(map))),
-            "AgentCalled" => Ok(Self::AgentCalled(AgenteCalled::parse_from_map(map))),
-            "AgentConnect" => Ok(Self::AgentConnect(AgentConnect::parse_from_map(map))),
-            "AgentComplete" => Ok(Self::AgentComplete(AgentComplete::parse_from_map(map))),
+            "QueueMemberStatus" => Ok(Self::MemberStatus(Member::parse_from_map(map)?)),
+            "QueueParams" => Ok(Self::Params(Params::parse_from_map(map)?)),
+            "QueueSummary" => Ok(Self::Summary(Summary::parse_from_map(map)?)),
+            "QueueSummaryComplete" => Ok(Self::SummaryComplete(StatusComplete::parse_from_map(
+                map,
+            )?)),
+            "AgentCalled" => Ok(Self::AgentCalled(AgenteCalled::parse_from_map(map)?)),
+            "AgentConnect" => Ok(Self::AgentConnect(AgentConnect::parse_from_map(map)?)),
+            "AgentComplete" => Ok(Self::AgentComplete(AgentComplete::parse_from_map(map)?)),
             "AgentRingNoAnswer" => Ok(Self::AgentRingNoAnswer(AgentRingNoAnswer::parse_from_map(
                 map,
+            )?)),
+            "QueueMember" => Ok(Self::Member(Member::parse_from_map(map)?)),
+            "AgentDump" => Ok(Self::AgentDump(AgentDump::parse_from_map(map)?)),
+            "AgentLogin" => Ok(Self::AgentLogin(AgentLogin::parse_from_map(map)?)),
+            "AgentLogoff" => Ok(Self::AgentLogoff(AgentLogoff::parse_from_map(map)?)),
+            "Newchannel" => Ok(Self::Newchannel(Channel::parse_from_map(map)?)),
+            "Newstate" => Ok(Self::Newstate(Channel::parse_from_map(map)?)),
+            "Hangup" => Ok(Self::Hangup(Hangup::parse_from_map(map)?)),
+            "DialBegin" => Ok(Self::DialBegin(Dial::parse_from_map(map)?)),
+            "DialEnd" => Ok(Self::DialEnd(Dial::parse_from_map(map)?)),
+            "BridgeCreate" => Ok(Self::BridgeCreate(Bridge::parse_from_map(map)?)),
+            "BridgeEnter" => Ok(Self::BridgeEnter(BridgeChannel::parse_from_map(map)?)),
+            "BridgeLeave" => Ok(Self::BridgeLeave(BridgeChannel::parse_from_map(map)?)),
+            "BridgeDestroy" => Ok(Self::BridgeDestroy(Bridge::parse_from_map(map)?)),
+            "AttendedTransfer" => Ok(Self::AttendedTransfer(Box::new(
+                AttendedTransfer::parse_from_map(map)?,
             ))),
-            "QueueMember" => Ok(Self::Member(Member::parse_from_map(map))),
-            "AgentDump" => Ok(Self::AgentDump(AgentDump::parse_from_map(map))),
-            "QueueMemberPaused" => Ok(Self::MemberPaused(Member::parse_from_map(map))),
-            "QueueMemberAdded" => Ok(Self::MemberAdded(Member::parse_from_map(map))),
-            "QueueMemberRemoved" => Ok(Self::MemberRemoved(Member::parse_from_map(map))),
+            "BlindTransfer" => Ok(Self::BlindTransfer(BlindTransfer::parse_from_map(map)?)),
+            "Hold" => Ok(Self::Hold(Hold::parse_from_map(map)?)),
+            "Unhold" => Ok(Self::Unhold(Hold::parse_from_map(map)?)),
+            "MusicOnHoldStart" => Ok(Self::MusicOnHoldStart(Hold::parse_from_map(map)?)),
+            "MusicOnHoldStop" => Ok(Self::MusicOnHoldStop(Hold::parse_from_map(map)?)),
+            "QueueMemberPaused" | "QueueMemberPause" => {
+                Ok(Self::MemberPaused(Member::parse_from_map(map)?))
+            }
+            "QueueMemberAdded" => Ok(Self::MemberAdded(Member::parse_from_map(map)?)),
+            "QueueMemberRemoved" => Ok(Self::MemberRemoved(Member::parse_from_map(map)?)),
             "MemberRingninuse" => Ok(Self::MemberRingninuse(MemberRingninuse::parse_from_map(
                 map,
-            ))),
-            "QueueEntry" => Ok(Self::Entry(Entry::parse_from_map(map))),
+            )?)),
+            "QueueEntry" => Ok(Self::Entry(Entry::parse_from_map(map)?)),
             "QueueCallerJoin" => Ok(Self::CallerJoin(
-                Caller::parse_from_map(map).r#type(TypeCallerEvent::Join),
+                Caller::parse_from_map(map)?.r#type(TypeCallerEvent::Join),
             )),
             "QueueCallerLeave" => Ok(Self::CallerLeave(
-                Caller::parse_from_map(map).r#type(TypeCallerEvent::Leave),
+                Caller::parse_from_map(map)?.r#type(TypeCallerEvent::Leave),
             )),
             "QueueCallerAbandon" => Ok(Self::CallerAbandon(
-                Caller::parse_from_map(map).r#type(TypeCallerEvent::Abandon),
+                Caller::parse_from_map(map)?.r#type(TypeCallerEvent::Abandon),
             )),
-            _ => Ok(Self::None),
+            _ => Ok(Self::Unknown {
+                event: event.to_string(),
+                headers: map
+                    .iter()
+                    .filter(|(key, _)| !key.eq_ignore_ascii_case("Event"))
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+            }),
         }
     }
 }
@@ -260,13 +746,17 @@ impl TryFrom<&str> for AmiMessage {
 impl std::fmt::Display for AmiMessage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            AmiMessage::None => write!(f, "None"),
+            AmiMessage::Unknown { event, .. } => write!(f, "{event}"),
+            AmiMessage::Greeting(greeting) => write!(f, "{}", greeting.banner),
             AmiMessage::Response(_) => write!(f, "MemberRingninuse"),
+            AmiMessage::Snapshot(_) => write!(f, "QueueStatus"),
             AmiMessage::MemberRingninuse(_) => write!(f, "MemberRingninuse"),
             AmiMessage::Member(_) => write!(f, "QueueMember"),
             AmiMessage::Params(_) => write!(f, "QueueParams"),
             AmiMessage::Entry(_) => write!(f, "QueueEntry"),
             AmiMessage::StatusComplete(_) => write!(f, "QueueStatusComplete"),
+            AmiMessage::Summary(_) => write!(f, "QueueSummary"),
+            AmiMessage::SummaryComplete(_) => write!(f, "QueueSummaryComplete"),
             AmiMessage::CallerJoin(_) => write!(f, "QueueCallerJoin"),
             AmiMessage::CallerLeave(_) => write!(f, "QueueCallerLeave"),
             AmiMessage::CallerAbandon(_) => write!(f, "QueueCallerAbandon"),
@@ -279,6 +769,23 @@ impl std::fmt::Display for AmiMessage {
             AmiMessage::AgentComplete(_) => write!(f, "AgentComplete"),
             AmiMessage::AgentRingNoAnswer(_) => write!(f, "AgentRingNoAnswer"),
             AmiMessage::AgentDump(_) => write!(f, "AgentDump"),
+            AmiMessage::AgentLogin(_) => write!(f, "AgentLogin"),
+            AmiMessage::AgentLogoff(_) => write!(f, "AgentLogoff"),
+            AmiMessage::Newchannel(_) => write!(f, "Newchannel"),
+            AmiMessage::Newstate(_) => write!(f, "Newstate"),
+            AmiMessage::Hangup(_) => write!(f, "Hangup"),
+            AmiMessage::DialBegin(_) => write!(f, "DialBegin"),
+            AmiMessage::DialEnd(_) => write!(f, "DialEnd"),
+            AmiMessage::BridgeCreate(_) => write!(f, "BridgeCreate"),
+            AmiMessage::BridgeEnter(_) => write!(f, "BridgeEnter"),
+            AmiMessage::BridgeLeave(_) => write!(f, "BridgeLeave"),
+            AmiMessage::BridgeDestroy(_) => write!(f, "BridgeDestroy"),
+            AmiMessage::AttendedTransfer(_) => write!(f, "AttendedTransfer"),
+            AmiMessage::BlindTransfer(_) => write!(f, "BlindTransfer"),
+            AmiMessage::Hold(_) => write!(f, "Hold"),
+            AmiMessage::Unhold(_) => write!(f, "Unhold"),
+            AmiMessage::MusicOnHoldStart(_) => write!(f, "MusicOnHoldStart"),
+            AmiMessage::MusicOnHoldStop(_) => write!(f, "MusicOnHoldStop"),
         }
     }
 }
@@ -286,22 +793,17 @@ impl std::fmt::Display for AmiMessage {
 pub struct EventGenMap;
 
 impl EventGenMap {
-    pub fn gen_map(data: &str) -> HashMap<&str, &str> {
-        data.lines()
-            .map(|x| {
-                let (key, value) = x.split_once(':').unwrap_or_default();
-                (key.trim(), value.trim())
-            })
-            .collect()
+    pub fn gen_map(data: &str) -> Headers<'_> {
+        Headers::parse(data)
     }
 }
 
 pub trait ParserEvent {
-    fn parse_from_map(data: HashMap<&str, &str>) -> Self
+    fn parse_from_map(data: Headers<'_>) -> Result<Self, AmiError>
     where
         Self: Sized;
 
-    fn parser(data: &str) -> Self
+    fn parser(data: &str) -> Result<Self, AmiError>
     where
         Self: Sized,
     {
@@ -0,0 +1,93 @@
+/// Headers of a frame in the order they were received.
+///
+/// The keys can be repeated (e.g. `ChanVariable`) and the lookup is case-insensitive,
+/// the values are slices of the frame
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Headers<'a>(Vec<(&'a str, &'a str)>);
+
+impl<'a> Headers<'a> {
+    /// The lines without `:` are ignored
+    pub fn parse(data: &'a str) -> Self {
+        data.lines()
+            .filter_map(|x| x.split_once(':'))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect()
+    }
+
+    /// Value of the first header with the key
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        self.get_all(key).next()
+    }
+
+    /// Values of all the headers with the key, in order
+    pub fn get_all<'b>(&'b self, key: &'b str) -> impl Iterator<Item = &'a str> + 'b {
+        self.0
+            .iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| *v)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Variables sent as `Key(name): value` or as `Key: name=value`, in order
+    pub fn variables(&self, key: &str) -> Vec<(&'a str, &'a str)> {
+        self.0
+            .iter()
+            .filter_map(|(k, v)| {
+                if k.eq_ignore_ascii_case(key) {
+                    return v.split_once('=').map(|(name, value)| (name.trim(), value.trim()));
+                }
+
+                let name = k
+                    .get(..key.len())
+                    .filter(|prefix| prefix.eq_ignore_ascii_case(key))
+                    .and_then(|_| k[key.len()..].strip_prefix('('))?
+                    .strip_suffix(')')?;
+                Some((name, *v))
+            })
+            .collect()
+    }
+
+    /// Headers with the key starting with `prefix`, without it.
+    /// e.g. `DestChannel` is `Channel` with the prefix `Dest`
+    pub fn prefixed(&self, prefix: &str) -> Headers<'a> {
+        self.0
+            .iter()
+            .filter_map(|(k, v)| {
+                k.get(..prefix.len())
+                    .filter(|x| x.eq_ignore_ascii_case(prefix))
+                    .map(|_| (&k[prefix.len()..], *v))
+            })
+            .filter(|(k, _)| !k.is_empty())
+            .collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a str)> + '_ {
+        self.0.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<'a> FromIterator<(&'a str, &'a str)> for Headers<'a> {
+    fn from_iter<T: IntoIterator<Item = (&'a str, &'a str)>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<'a> IntoIterator for Headers<'a> {
+    type Item = (&'a str, &'a str);
+    type IntoIter = std::vec::IntoIter<(&'a str, &'a str)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
@@ -1,25 +1,314 @@
-use futures::StreamExt;
-use tokio::net::TcpStream;
+use std::{path::PathBuf, time::Duration};
 
-use crate::asterisk::event::EventHandler;
+use futures::{StreamExt, future::OptionFuture};
+use tokio::sync::mpsc::UnboundedSender;
 
+use crate::asterisk::{
+    client::{AmiClient, ClientReceiver},
+    connection::{Backoff, ConnectionState},
+    error::AmiError,
+    event::{
+        AmiMessage, Charset, EventHandler, LOGIN_TIMEOUT, LoginMode, MAX_BUFFERED, MAX_FRAME_SIZE,
+    },
+    state::QueueState,
+    transport::{BoxReader, BoxWriter, CONNECT_TIMEOUT, Transport},
+};
+use crate::io::capture::RecordReader;
+
+pub mod action;
+pub mod call;
+pub mod client;
+pub mod connection;
 pub mod entities;
+pub mod error;
 pub mod event;
+pub mod headers;
+pub mod queue_action;
+pub mod state;
+pub mod transport;
+pub mod version;
+
+/// Events sent by [`Alma`] to the consumers
+#[derive(Debug)]
+pub enum AlmaEvent {
+    Connection(ConnectionState),
+    Message(Box<AmiMessage>),
+    Error(AmiError),
+}
 
-pub struct Alma;
+/// Supervised connection with the AMI server.
+/// When the connection is lost it is opened again following the [`Backoff`]
+pub struct Alma {
+    socket: String,
+    user: String,
+    secret: String,
+    backoff: Backoff,
+    client: AmiClient,
+    commands: Option<ClientReceiver>,
+    state: QueueState,
+    resync: Option<Duration>,
+    stop_on_auth_failure: bool,
+    login_mode: LoginMode,
+    transport: Transport,
+    record: Option<PathBuf>,
+    max_frame_size: usize,
+    max_buffered: usize,
+    charset: Charset,
+    call_events: bool,
+    connect_timeout: Duration,
+    login_timeout: Duration,
+}
 
 impl Alma {
-    pub async fn run(socket: String, user: String, secret: String) {
-        let stream = TcpStream::connect(socket).await.unwrap();
+    pub fn new(socket: String, user: String, secret: String) -> Self {
+        let (client, commands) = AmiClient::channel();
+        Self {
+            socket,
+            user,
+            secret,
+            backoff: Backoff::default(),
+            client,
+            commands: Some(commands),
+            state: QueueState::new(),
+            resync: None,
+            stop_on_auth_failure: false,
+            login_mode: LoginMode::Plain,
+            transport: Transport::Tcp,
+            record: None,
+            max_frame_size: MAX_FRAME_SIZE,
+            max_buffered: MAX_BUFFERED,
+            charset: Charset::Utf8Lossy,
+            call_events: false,
+            connect_timeout: CONNECT_TIMEOUT,
+            login_timeout: LOGIN_TIMEOUT,
+        }
+    }
+
+    /// Reload the state of the queues every `period`
+    pub fn resync(mut self, period: Duration) -> Self {
+        self.resync = Some(period);
+        self
+    }
+
+    pub fn transport(mut self, transport: Transport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Use LoginMode::Md5 so the secret never crosses the network
+    pub fn login_mode(mut self, login_mode: LoginMode) -> Self {
+        self.login_mode = login_mode;
+        self
+    }
+
+    /// Save the bytes read from the AMI in a capture file, see [`crate::io::capture`].
+    /// After a reconnection the number of the connection is appended: `path.2`, `path.3`...
+    pub fn record(mut self, path: impl Into<PathBuf>) -> Self {
+        self.record = Some(path.into());
+        self
+    }
+
+    /// See [`EventHandler::call_events`], the calls are in [`QueueState::call`]
+    pub fn call_events(mut self, enabled: bool) -> Self {
+        self.call_events = enabled;
+        self
+    }
+
+    /// See [`EventHandler::charset`]
+    pub fn charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    /// See [`EventHandler::max_frame_size`]
+    pub fn max_frame_size(mut self, limit: usize) -> Self {
+        self.max_frame_size = limit;
+        self
+    }
+
+    /// See [`EventHandler::max_buffered`]
+    pub fn max_buffered(mut self, limit: usize) -> Self {
+        self.max_buffered = limit;
+        self
+    }
+
+    /// Max time to open the socket, with the TLS handshake
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
 
-        let mut tmp = EventHandler::new(stream, user, secret);
+    /// See [`EventHandler::login_timeout`]
+    pub fn login_timeout(mut self, timeout: Duration) -> Self {
+        self.login_timeout = timeout;
+        self
+    }
+
+    /// Stop running when the credentials are rejected.
+    /// By default it keeps retrying with the max delay of the backoff
+    pub fn stop_on_auth_failure(mut self, stop: bool) -> Self {
+        self.stop_on_auth_failure = stop;
+        self
+    }
+
+    /// Handle to send actions, it keeps working after a reconnection
+    pub fn client(&self) -> AmiClient {
+        self.client.clone()
+    }
+
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Live state of the queues, updated by [`Alma::run`]
+    pub fn state(&self) -> QueueState {
+        self.state.clone()
+    }
+
+    /// Runs until the receiver of `tx` is dropped
+    pub async fn run(mut self, tx: UnboundedSender<AlmaEvent>) {
+        let mut handler: Option<EventHandler<BoxReader, BoxWriter>> = None;
+        let mut connections = 0;
 
         loop {
-            let tmp = tmp.next().await;
-            if tmp.is_none() {
-                break;
+            if tx.send(AlmaEvent::Connection(ConnectionState::Connecting)).is_err() {
+                return;
+            }
+
+            let connect = self.transport.connect(&self.socket);
+            let connection = tokio::time::timeout(self.connect_timeout, connect)
+                .await
+                .unwrap_or_else(|_| Err(AmiError::Timeout("the connection".to_string())));
+
+            match connection {
+                Ok((mut reader, writer)) => {
+                    if tx.send(AlmaEvent::Connection(ConnectionState::Connected)).is_err() {
+                        return;
+                    }
+
+                    connections += 1;
+                    let mut capture = None;
+                    if let Some(path) = &self.record {
+                        let mut path = path.clone();
+                        if connections > 1 {
+                            path.as_mut_os_string().push(format!(".{connections}"));
+                        }
+                        match tokio::fs::File::create(&path).await {
+                            Ok(file) => {
+                                let (record, end) = RecordReader::new(reader, file);
+                                reader = Box::new(record);
+                                capture = Some(end);
+                            }
+                            Err(er) => {
+                                if tx.send(AlmaEvent::Error(AmiError::Capture(er))).is_err() {
+                                    return;
+                                }
+                            }
+                        }
+                    }
+
+                    let handler = match handler.as_mut() {
+                        Some(handler) => {
+                            handler.reconnect(reader, writer);
+                            handler
+                        }
+                        None => {
+                            let mut new = EventHandler::from_split(
+                                reader,
+                                writer,
+                                self.user.clone(),
+                                self.secret.clone(),
+                            )
+                            .login_mode(self.login_mode)
+                            .max_frame_size(self.max_frame_size)
+                            .max_buffered(self.max_buffered)
+                            .charset(self.charset)
+                            .call_events(self.call_events)
+                            .login_timeout(self.login_timeout);
+                            if let Some(commands) = self.commands.take() {
+                                new = new.channel(self.client.clone(), commands);
+                            }
+                            if let Some(period) = self.resync {
+                                new = new.resync(period);
+                            }
+                            handler.insert(new)
+                        }
+                    };
+
+                    loop {
+                        let msg = tokio::select! {
+                            msg = handler.next() => msg,
+                            Some(result) = OptionFuture::from(capture.as_mut()) => {
+                                capture = None;
+                                if let Err(er) = result
+                                    && tx.send(AlmaEvent::Error(AmiError::Capture(er))).is_err()
+                                {
+                                    return;
+                                }
+                                continue;
+                            }
+                        };
+                        let Some(msg) = msg else {
+                            break;
+                        };
+
+                        let event = match msg {
+                            Ok(msg) => {
+                                if let Err(er) = self.state.apply(&msg)
+                                    && tx.send(AlmaEvent::Error(er)).is_err()
+                                {
+                                    return;
+                                }
+                                AlmaEvent::Message(Box::new(msg))
+                            }
+                            Err(er) => {
+                                if matches!(er, AmiError::AuthenticationRejected(_)) {
+                                    if self.stop_on_auth_failure {
+                                        _ = tx.send(AlmaEvent::Error(er));
+                                        _ = tx.send(AlmaEvent::Connection(ConnectionState::Disconnected));
+                                        return;
+                                    }
+                                    self.backoff.saturate();
+                                }
+                                AlmaEvent::Error(er)
+                            }
+                        };
+
+                        if tx.send(event).is_err() {
+                            return;
+                        }
+                    }
+
+                    // The login was accepted, the next attempt starts from the initial delay.
+                    // A server that closes before it (e.g. after the banner) keeps increasing the delay
+                    if handler.logged_in() {
+                        self.backoff.reset();
+                    }
+
+                    self.state.disconnected();
+                    if tx.send(AlmaEvent::Connection(ConnectionState::Disconnected)).is_err() {
+                        return;
+                    }
+                }
+                Err(er) => {
+                    if tx.send(AlmaEvent::Error(er)).is_err() {
+                        return;
+                    }
+                }
+            }
+
+            let delay = self.backoff.next_delay();
+            let state = ConnectionState::Reconnecting {
+                attempt: self.backoff.attempt(),
+                delay,
+            };
+
+            if tx.send(AlmaEvent::Connection(state)).is_err() {
+                return;
             }
-            println!("{tmp:?}");
+
+            tokio::time::sleep(delay).await;
         }
     }
 }
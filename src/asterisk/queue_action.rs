@@ -0,0 +1,249 @@
+use crate::asterisk::{
+    action::{Action, FollowUp, FollowUpEvent},
+    entities::member::Member,
+};
+
+/// Action: QueuePause
+///
+/// Pause or unpause a member. Without queue the member is paused in every queue
+/// and the action is resolved with the first QueueMemberPause event
+#[derive(Debug, Clone)]
+pub struct QueuePause {
+    pub interface: String,
+    pub paused: bool,
+    pub queue: Option<String>,
+    pub reason: Option<String>,
+}
+
+impl QueuePause {
+    pub fn new(interface: impl Into<String>, paused: bool) -> Self {
+        Self {
+            interface: interface.into(),
+            paused,
+            queue: None,
+            reason: None,
+        }
+    }
+
+    pub fn from_member(member: &Member, paused: bool) -> Self {
+        Self::new(member.interface.clone(), paused).queue(member.queue.clone())
+    }
+
+    pub fn queue(mut self, queue: impl Into<String>) -> Self {
+        self.queue = Some(queue.into());
+        self
+    }
+
+    pub fn reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+}
+
+impl From<QueuePause> for Action {
+    fn from(value: QueuePause) -> Self {
+        Action::new("QueuePause")
+            .header("Interface", value.interface.clone())
+            .header("Paused", value.paused.to_string())
+            .header_opt("Queue", value.queue.clone())
+            .header_opt("Reason", value.reason)
+            .follow_up(FollowUp {
+                event: FollowUpEvent::MemberPaused,
+                queue: value.queue,
+                interface: value.interface,
+            })
+    }
+}
+
+/// Action: QueueAdd
+#[derive(Debug, Clone)]
+pub struct QueueAdd {
+    pub queue: String,
+    pub interface: String,
+    pub penalty: Option<u32>,
+    pub paused: bool,
+    pub member_name: Option<String>,
+    pub state_interface: Option<String>,
+}
+
+impl QueueAdd {
+    pub fn new(queue: impl Into<String>, interface: impl Into<String>) -> Self {
+        Self {
+            queue: queue.into(),
+            interface: interface.into(),
+            penalty: None,
+            paused: false,
+            member_name: None,
+            state_interface: None,
+        }
+    }
+
+    pub fn penalty(mut self, penalty: u32) -> Self {
+        self.penalty = Some(penalty);
+        self
+    }
+
+    pub fn paused(mut self, paused: bool) -> Self {
+        self.paused = paused;
+        self
+    }
+
+    pub fn member_name(mut self, member_name: impl Into<String>) -> Self {
+        self.member_name = Some(member_name.into());
+        self
+    }
+
+    pub fn state_interface(mut self, state_interface: impl Into<String>) -> Self {
+        self.state_interface = Some(state_interface.into());
+        self
+    }
+}
+
+impl From<QueueAdd> for Action {
+    fn from(value: QueueAdd) -> Self {
+        Action::new("QueueAdd")
+            .header("Queue", value.queue.clone())
+            .header("Interface", value.interface.clone())
+            .header_opt("Penalty", value.penalty.map(|x| x.to_string()))
+            .header("Paused", value.paused.to_string())
+            .header_opt("MemberName", value.member_name)
+            .header_opt("StateInterface", value.state_interface)
+            .follow_up(FollowUp {
+                event: FollowUpEvent::MemberAdded,
+                queue: Some(value.queue),
+                interface: value.interface,
+            })
+    }
+}
+
+/// Action: QueueRemove
+#[derive(Debug, Clone)]
+pub struct QueueRemove {
+    pub queue: String,
+    pub interface: String,
+}
+
+impl QueueRemove {
+    pub fn new(queue: impl Into<String>, interface: impl Into<String>) -> Self {
+        Self {
+            queue: queue.into(),
+            interface: interface.into(),
+        }
+    }
+
+    pub fn from_member(member: &Member) -> Self {
+        Self::new(member.queue.clone(), member.interface.clone())
+    }
+}
+
+impl From<QueueRemove> for Action {
+    fn from(value: QueueRemove) -> Self {
+        Action::new("QueueRemove")
+            .header("Queue", value.queue.clone())
+            .header("Interface", value.interface.clone())
+            .follow_up(FollowUp {
+                event: FollowUpEvent::MemberRemoved,
+                queue: Some(value.queue),
+                interface: value.interface,
+            })
+    }
+}
+
+/// Action: QueuePenalty
+///
+/// Without queue the penalty is changed in every queue of the member
+#[derive(Debug, Clone)]
+pub struct QueuePenalty {
+    pub interface: String,
+    pub penalty: u32,
+    pub queue: Option<String>,
+}
+
+impl QueuePenalty {
+    pub fn new(interface: impl Into<String>, penalty: u32) -> Self {
+        Self {
+            interface: interface.into(),
+            penalty,
+            queue: None,
+        }
+    }
+
+    pub fn from_member(member: &Member, penalty: u32) -> Self {
+        Self::new(member.interface.clone(), penalty).queue(member.queue.clone())
+    }
+
+    pub fn queue(mut self, queue: impl Into<String>) -> Self {
+        self.queue = Some(queue.into());
+        self
+    }
+}
+
+impl From<QueuePenalty> for Action {
+    fn from(value: QueuePenalty) -> Self {
+        Action::new("QueuePenalty")
+            .header("Interface", value.interface)
+            .header("Penalty", value.penalty.to_string())
+            .header_opt("Queue", value.queue)
+    }
+}
+
+/// Action: QueueMemberRingInUse
+#[derive(Debug, Clone)]
+pub struct QueueMemberRingInUse {
+    pub interface: String,
+    pub ring_in_use: bool,
+    pub queue: Option<String>,
+}
+
+impl QueueMemberRingInUse {
+    pub fn new(interface: impl Into<String>, ring_in_use: bool) -> Self {
+        Self {
+            interface: interface.into(),
+            ring_in_use,
+            queue: None,
+        }
+    }
+
+    pub fn from_member(member: &Member, ring_in_use: bool) -> Self {
+        Self::new(member.interface.clone(), ring_in_use).queue(member.queue.clone())
+    }
+
+    pub fn queue(mut self, queue: impl Into<String>) -> Self {
+        self.queue = Some(queue.into());
+        self
+    }
+}
+
+impl From<QueueMemberRingInUse> for Action {
+    fn from(value: QueueMemberRingInUse) -> Self {
+        Action::new("QueueMemberRingInUse")
+            .header("Interface", value.interface)
+            .header("RingInUse", value.ring_in_use.to_string())
+            .header_opt("Queue", value.queue)
+    }
+}
+
+/// Action: QueueSummary
+///
+/// One QueueSummary event per queue, see [`AmiClient::queue_summary`](crate::asterisk::client::AmiClient::queue_summary)
+#[derive(Debug, Clone, Default)]
+pub struct QueueSummary {
+    pub queue: Option<String>,
+}
+
+impl QueueSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn queue(mut self, queue: impl Into<String>) -> Self {
+        self.queue = Some(queue.into());
+        self
+    }
+}
+
+impl From<QueueSummary> for Action {
+    fn from(value: QueueSummary) -> Self {
+        Action::new("QueueSummary").header_opt("Queue", value.queue)
+    }
+}
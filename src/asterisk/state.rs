@@ -0,0 +1,440 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::sync::broadcast;
+
+use crate::asterisk::{
+    action::ActionResponse,
+    call::{AgentCall, Call, Calls},
+    entities::{Entry, Params, caller::Caller, member::Member},
+    error::AmiError,
+    event::AmiMessage,
+};
+
+/// Event list of Action: QueueStatus, applied to the state as a whole
+///
+/// list_items: ListItems of QueueStatusComplete, None if the list didn't finish
+/// received: number of events received before QueueStatusComplete
+#[derive(Debug, Clone, Default)]
+pub struct QueueSnapshot {
+    pub params: Vec<Params>,
+    pub members: Vec<Member>,
+    pub entries: Vec<Entry>,
+    pub list_items: Option<i32>,
+    pub received: usize,
+}
+
+impl QueueSnapshot {
+    /// The server sent the whole list and nothing was lost
+    pub fn is_complete(&self) -> bool {
+        self.list_items
+            .is_some_and(|x| usize::try_from(x).is_ok_and(|x| x == self.received))
+    }
+}
+
+impl From<ActionResponse> for QueueSnapshot {
+    fn from(value: ActionResponse) -> Self {
+        let mut snapshot = Self::default();
+
+        if !value.response.is_ok() {
+            return snapshot;
+        }
+
+        for event in value.events {
+            match event {
+                AmiMessage::Params(params) => snapshot.params.push(params),
+                AmiMessage::Member(member) => snapshot.members.push(member),
+                AmiMessage::Entry(entry) => snapshot.entries.push(entry),
+                AmiMessage::StatusComplete(complete) => {
+                    snapshot.list_items = Some(complete.len);
+                    continue;
+                }
+                _ => {}
+            }
+            snapshot.received += 1;
+        }
+
+        snapshot
+    }
+}
+
+/// Caller waiting in a queue
+#[derive(Debug, Clone)]
+pub struct WaitingCaller {
+    pub unique_id: String,
+    pub caller_id_num: String,
+    pub caller_id_name: String,
+    pub position: u16,
+    pub joined_at: Instant,
+}
+
+impl WaitingCaller {
+    pub fn wait(&self) -> Duration {
+        self.joined_at.elapsed()
+    }
+}
+
+impl From<&Caller> for WaitingCaller {
+    fn from(value: &Caller) -> Self {
+        Self {
+            unique_id: value.callet_unique_id.clone(),
+            caller_id_num: value.caller_id_num.clone(),
+            caller_id_name: value.caller_id_name.clone(),
+            position: value.position,
+            joined_at: Instant::now(),
+        }
+    }
+}
+
+impl From<&Entry> for WaitingCaller {
+    fn from(value: &Entry) -> Self {
+        let now = Instant::now();
+        Self {
+            unique_id: value.unique_id.clone(),
+            caller_id_num: value.caller_id_number.clone(),
+            caller_id_name: value.caller_id_name.clone(),
+            position: value.position,
+            joined_at: now
+                .checked_sub(Duration::from_secs(value.wait))
+                .unwrap_or(now),
+        }
+    }
+}
+
+/// State of one queue
+///
+/// params: last QueueParams, the counters are updated with the live events
+/// members: queue members by interface
+/// callers: callers waiting, ordered by position
+/// ring_no_answer: AgentRingNoAnswer received since the monitor started
+#[derive(Debug, Clone, Default)]
+pub struct Queue {
+    pub params: Params,
+    pub members: HashMap<String, Member>,
+    pub callers: Vec<WaitingCaller>,
+    pub ring_no_answer: u32,
+}
+
+impl Queue {
+    fn new(name: &str) -> Self {
+        let mut queue = Self::default();
+        queue.params.queue = name.to_string();
+        queue
+    }
+
+    fn join(&mut self, caller: WaitingCaller) {
+        self.callers.retain(|x| x.unique_id != caller.unique_id);
+        let index = self
+            .callers
+            .partition_point(|x| x.position < caller.position);
+        self.callers.insert(index, caller);
+        self.params.calls = self.callers.len() as u32;
+    }
+
+    fn leave(&mut self, unique_id: &str) {
+        let Some(index) = self.callers.iter().position(|x| x.unique_id == unique_id) else {
+            return;
+        };
+        let caller = self.callers.remove(index);
+        for x in self.callers.iter_mut().filter(|x| x.position > caller.position) {
+            x.position -= 1;
+        }
+        self.params.calls = self.callers.len() as u32;
+    }
+}
+
+/// Change applied to the state, sent to the subscribers
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueueChange {
+    /// Every queue was replaced by a QueueStatus snapshot
+    Snapshot,
+    Params { queue: String },
+    Member { queue: String, interface: String },
+    Caller { queue: String, unique_id: String },
+
+    /// A channel event of the call, only with the class `call` subscribed
+    Call { linked_id: String },
+}
+
+/// Live state of the queues, built from the [`AmiMessage`].
+/// It can be cloned and read from other tasks
+#[derive(Debug, Clone)]
+pub struct QueueState {
+    queues: Arc<RwLock<HashMap<String, Queue>>>,
+    calls: Arc<RwLock<Calls>>,
+    notify: broadcast::Sender<QueueChange>,
+}
+
+impl Default for QueueState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QueueState {
+    pub fn new() -> Self {
+        let (notify, _) = broadcast::channel(256);
+        Self {
+            queues: Arc::default(),
+            calls: Arc::default(),
+            notify,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<QueueChange> {
+        self.notify.subscribe()
+    }
+
+    pub fn queue(&self, name: &str) -> Option<Queue> {
+        self.queues.read().unwrap().get(name).cloned()
+    }
+
+    pub fn queues(&self) -> Vec<Queue> {
+        self.queues.read().unwrap().values().cloned().collect()
+    }
+
+    pub fn queue_names(&self) -> Vec<String> {
+        self.queues.read().unwrap().keys().cloned().collect()
+    }
+
+    /// Call of a channel by its Uniqueid or Linkedid, e.g. the unique_id of a [`WaitingCaller`]
+    pub fn call(&self, id: &str) -> Option<Call> {
+        self.calls.read().unwrap().get(id).cloned()
+    }
+
+    pub fn active_calls(&self) -> Vec<Call> {
+        self.calls.read().unwrap().active().cloned().collect()
+    }
+
+    /// Agent call by the Uniqueid of the caller, with its holds and transfer
+    pub fn agent_call(&self, unique_id: &str) -> Option<AgentCall> {
+        self.calls.read().unwrap().agent_call(unique_id).cloned()
+    }
+
+    /// Agent calls in progress
+    pub fn agent_calls(&self) -> Vec<AgentCall> {
+        self.calls.read().unwrap().agent_calls().cloned().collect()
+    }
+
+    /// Last agent calls completed, the oldest first
+    pub fn completed_agent_calls(&self) -> Vec<AgentCall> {
+        self.calls
+            .read()
+            .unwrap()
+            .completed_agent_calls()
+            .cloned()
+            .collect()
+    }
+
+    /// Discard the calls in progress, the events that end them are lost while disconnected.
+    /// The completed agent calls are kept
+    pub fn disconnected(&self) {
+        self.calls.write().unwrap().clear_active();
+    }
+
+    /// Update the state with the message.
+    /// The messages that don't belong to a queue or a call are ignored.
+    /// An incomplete snapshot is discarded with AmiError::IncompleteSnapshot, the state is stale until the next one
+    pub fn apply(&self, message: &AmiMessage) -> Result<(), AmiError> {
+        if let AmiMessage::Snapshot(snapshot) = message
+            && !snapshot.is_complete()
+        {
+            return Err(AmiError::IncompleteSnapshot {
+                expected: snapshot.list_items,
+                received: snapshot.received,
+            });
+        }
+
+        let change = {
+            let mut queues = self.queues.write().unwrap();
+            Self::apply_to(&mut queues, message)
+        };
+        let call = self.calls.write().unwrap().apply(message);
+
+        if let Some(change) = change {
+            _ = self.notify.send(change);
+        }
+        if let Some(linked_id) = call {
+            _ = self.notify.send(QueueChange::Call { linked_id });
+        }
+        Ok(())
+    }
+
+    fn apply_to(queues: &mut HashMap<String, Queue>, message: &AmiMessage) -> Option<QueueChange> {
+        fn queue<'a>(queues: &'a mut HashMap<String, Queue>, name: &str) -> &'a mut Queue {
+            queues
+                .entry(name.to_string())
+                .or_insert_with(|| Queue::new(name))
+        }
+
+        fn member_change(member: &Member) -> QueueChange {
+            QueueChange::Member {
+                queue: member.queue.clone(),
+                interface: member.interface.clone(),
+            }
+        }
+
+        match message {
+            AmiMessage::Snapshot(snapshot) => {
+                Self::replace(queues, snapshot);
+                Some(QueueChange::Snapshot)
+            }
+            AmiMessage::Params(params) => {
+                let queue = queue(queues, &params.queue);
+                queue.params = params.clone();
+                Some(QueueChange::Params {
+                    queue: params.queue.clone(),
+                })
+            }
+            AmiMessage::Entry(entry) => {
+                queue(queues, &entry.queue).join(entry.into());
+                Some(QueueChange::Caller {
+                    queue: entry.queue.clone(),
+                    unique_id: entry.unique_id.clone(),
+                })
+            }
+            AmiMessage::CallerJoin(caller) => {
+                queue(queues, &caller.queue).join(caller.into());
+                Some(QueueChange::Caller {
+                    queue: caller.queue.clone(),
+                    unique_id: caller.callet_unique_id.clone(),
+                })
+            }
+            AmiMessage::CallerLeave(caller) => {
+                queue(queues, &caller.queue).leave(&caller.callet_unique_id);
+                Some(QueueChange::Caller {
+                    queue: caller.queue.clone(),
+                    unique_id: caller.callet_unique_id.clone(),
+                })
+            }
+            AmiMessage::CallerAbandon(caller) => {
+                // QueueCallerLeave is raised after this event, it removes the caller
+                queue(queues, &caller.queue).params.abandoned += 1;
+                Some(QueueChange::Params {
+                    queue: caller.queue.clone(),
+                })
+            }
+            AmiMessage::Member(member)
+            | AmiMessage::MemberStatus(member)
+            | AmiMessage::MemberAdded(member) => {
+                queue(queues, &member.queue)
+                    .members
+                    .insert(member.interface.clone(), member.clone());
+                Some(member_change(member))
+            }
+            AmiMessage::MemberPaused(member) => {
+                let queue = queue(queues, &member.queue);
+                match queue.members.get_mut(&member.interface) {
+                    Some(current) => {
+                        current.paused = member.paused;
+                        current.pause_reason = member.pause_reason.clone();
+                    }
+                    None => {
+                        queue
+                            .members
+                            .insert(member.interface.clone(), member.clone());
+                    }
+                }
+                Some(member_change(member))
+            }
+            AmiMessage::MemberRemoved(member) => {
+                queue(queues, &member.queue)
+                    .members
+                    .remove(&member.interface);
+                Some(member_change(member))
+            }
+            AmiMessage::AgentConnect(agent) => {
+                let queue = queue(queues, &agent.queue);
+                queue.params.hold_time = average(queue.params.hold_time, agent.hold_time);
+                if let Some(member) = queue.members.get_mut(&agent.interface) {
+                    member.in_call = true;
+                }
+                Some(QueueChange::Member {
+                    queue: agent.queue.clone(),
+                    interface: agent.interface.clone(),
+                })
+            }
+            AmiMessage::AgentComplete(agent) => {
+                let queue = queue(queues, &agent.queue);
+                queue.params.completed += 1;
+                queue.params.talk_time = average(queue.params.talk_time, agent.talk_time);
+                if let Some(member) = queue.members.get_mut(&agent.interface) {
+                    member.in_call = false;
+                    member.calls_taken = member.calls_taken.saturating_add(1);
+                    member.last_call = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|x| x.as_secs())
+                        .unwrap_or_default();
+                }
+                Some(QueueChange::Member {
+                    queue: agent.queue.clone(),
+                    interface: agent.interface.clone(),
+                })
+            }
+            AmiMessage::AgentDump(agent) => {
+                if let Some(member) = queue(queues, &agent.queue)
+                    .members
+                    .get_mut(&agent.interface)
+                {
+                    member.in_call = false;
+                }
+                Some(QueueChange::Member {
+                    queue: agent.queue.clone(),
+                    interface: agent.interface.clone(),
+                })
+            }
+            AmiMessage::AgentRingNoAnswer(agent) => {
+                queue(queues, &agent.queue).ring_no_answer += 1;
+                Some(QueueChange::Member {
+                    queue: agent.queue.clone(),
+                    interface: agent.interface.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl QueueState {
+    /// Replace the queues with the snapshot, only the counters of the monitor are kept
+    fn replace(queues: &mut HashMap<String, Queue>, snapshot: &QueueSnapshot) {
+        let mut new = HashMap::new();
+
+        for params in &snapshot.params {
+            let mut queue = Queue::new(&params.queue);
+            queue.params = params.clone();
+            queue.ring_no_answer = queues
+                .get(&params.queue)
+                .map(|x| x.ring_no_answer)
+                .unwrap_or_default();
+            new.insert(params.queue.clone(), queue);
+        }
+
+        for member in &snapshot.members {
+            new.entry(member.queue.clone())
+                .or_insert_with(|| Queue::new(&member.queue))
+                .members
+                .insert(member.interface.clone(), member.clone());
+        }
+
+        for entry in &snapshot.entries {
+            new.entry(entry.queue.clone())
+                .or_insert_with(|| Queue::new(&entry.queue))
+                .join(entry.into());
+        }
+
+        for queue in new.values_mut() {
+            queue.params.calls = queue.callers.len() as u32;
+        }
+
+        *queues = new;
+    }
+}
+
+/// Same weighted average used by app_queue for the holdtime and talktime
+fn average(old: u64, new: u64) -> u64 {
+    (old * 3 + new) / 4
+}
@@ -0,0 +1,193 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    TlsConnector,
+    rustls::{
+        self, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        crypto::WebPkiSupportedAlgorithms,
+        pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime, pem::PemObject},
+    },
+};
+
+use crate::asterisk::error::AmiError;
+
+pub type BoxReader = Box<dyn AsyncRead + Send + Unpin>;
+pub type BoxWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// Default time to open the socket, with the TLS handshake
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How the socket with the AMI is opened
+#[derive(Debug, Clone, Default)]
+pub enum Transport {
+    /// Plain TCP, usually port 5038
+    #[default]
+    Tcp,
+
+    /// TLS over TCP, usually port 5039
+    Tls(TlsConfig),
+}
+
+/// TLS configuration, the files are PEM encoded
+///
+/// ca: certificates to validate the server, the webpki roots if None
+/// cert, key: client certificate, only if the server asks for it
+/// pinned: the server must present exactly this certificate, the CA is not checked
+/// server_name: name to validate the certificate, the host of the socket if None
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    pub ca: Option<PathBuf>,
+    pub cert: Option<PathBuf>,
+    pub key: Option<PathBuf>,
+    pub pinned: Option<PathBuf>,
+    pub server_name: Option<String>,
+}
+
+impl TlsConfig {
+    pub fn ca(mut self, ca: impl Into<PathBuf>) -> Self {
+        self.ca = Some(ca.into());
+        self
+    }
+
+    pub fn client_cert(mut self, cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        self.cert = Some(cert.into());
+        self.key = Some(key.into());
+        self
+    }
+
+    pub fn pinned(mut self, pinned: impl Into<PathBuf>) -> Self {
+        self.pinned = Some(pinned.into());
+        self
+    }
+
+    pub fn server_name(mut self, server_name: impl Into<String>) -> Self {
+        self.server_name = Some(server_name.into());
+        self
+    }
+
+    fn client_config(&self) -> Result<ClientConfig, AmiError> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?;
+
+        let builder = if let Some(pinned) = &self.pinned {
+            let pinned = CertificateDer::from_pem_file(pinned).map_err(tls_error)?;
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(PinnedCertificate {
+                    certificate: pinned,
+                    algorithms: provider.signature_verification_algorithms,
+                }))
+        } else {
+            let mut roots = RootCertStore::empty();
+            match &self.ca {
+                Some(ca) => {
+                    for cert in CertificateDer::pem_file_iter(ca).map_err(tls_error)? {
+                        roots.add(cert.map_err(tls_error)?).map_err(tls_error)?;
+                    }
+                }
+                None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+            }
+            builder.with_root_certificates(roots)
+        };
+
+        match (&self.cert, &self.key) {
+            (Some(cert), Some(key)) => {
+                let certs = CertificateDer::pem_file_iter(cert)
+                    .map_err(tls_error)?
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(tls_error)?;
+                let key = PrivateKeyDer::from_pem_file(key).map_err(tls_error)?;
+                builder.with_client_auth_cert(certs, key).map_err(tls_error)
+            }
+            _ => Ok(builder.with_no_client_auth()),
+        }
+    }
+}
+
+impl Transport {
+    /// Open the socket and split it in reader and writer
+    pub async fn connect(&self, socket: &str) -> Result<(BoxReader, BoxWriter), AmiError> {
+        let stream = TcpStream::connect(socket).await?;
+
+        match self {
+            Transport::Tcp => {
+                let (reader, writer) = stream.into_split();
+                Ok((Box::new(reader), Box::new(writer)))
+            }
+            Transport::Tls(config) => {
+                let host = config.server_name.clone().unwrap_or_else(|| {
+                    socket
+                        .rsplit_once(':')
+                        .map(|(host, _)| host)
+                        .unwrap_or(socket)
+                        .trim_matches(['[', ']'])
+                        .to_string()
+                });
+                let server_name = ServerName::try_from(host).map_err(tls_error)?;
+                let connector = TlsConnector::from(Arc::new(config.client_config()?));
+                let stream = connector.connect(server_name, stream).await?;
+                let (reader, writer) = tokio::io::split(stream);
+                Ok((Box::new(reader), Box::new(writer)))
+            }
+        }
+    }
+}
+
+fn tls_error(er: impl std::fmt::Display) -> AmiError {
+    AmiError::Tls(er.to_string())
+}
+
+/// Accept only the pinned certificate, used with self-signed certificates of the PBX
+#[derive(Debug)]
+struct PinnedCertificate {
+    certificate: CertificateDer<'static>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for PinnedCertificate {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if end_entity.as_ref() == self.certificate.as_ref() {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(
+                "The certificate doesn't match the pinned certificate".to_string(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
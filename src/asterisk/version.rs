@@ -0,0 +1,76 @@
+use std::str::FromStr;
+
+/// First line sent by the server, e.g. `Asterisk Call Manager/7.0.3`
+///
+/// banner: the whole line
+/// version: version of the AMI protocol, None if the banner has an unknown format
+#[derive(Debug, Clone, PartialEq)]
+pub struct Greeting {
+    pub banner: String,
+    pub version: Option<AmiVersion>,
+}
+
+impl From<&str> for Greeting {
+    fn from(value: &str) -> Self {
+        let banner = value.trim();
+        Self {
+            banner: banner.to_string(),
+            version: banner
+                .rsplit_once('/')
+                .and_then(|(_, version)| version.parse().ok()),
+        }
+    }
+}
+
+/// Version of the AMI protocol, it isn't the version of Asterisk (see [`AmiVersion::asterisk`])
+///
+/// It's ordered, so the behavior can depend on it: `version >= AmiVersion::new(5, 0, 0)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AmiVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl AmiVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Major version of Asterisk that ships this AMI version:
+    /// 1.x is Asterisk 11 or older, 2.x is 12 / 13 and from 3.x (Asterisk 14) the major is +11,
+    /// so 5.x is Asterisk 16, 7.x is 18 and 9.x is 20
+    pub fn asterisk(&self) -> u32 {
+        match self.major {
+            0 | 1 => 11,
+            2 if self.minor == 0 => 12,
+            2 => 13,
+            major => major + 11,
+        }
+    }
+}
+
+impl FromStr for AmiVersion {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().splitn(3, '.');
+        let mut next = || parts.next().map(str::parse).transpose();
+
+        let major = next()?.unwrap_or_default();
+        let minor = next()?.unwrap_or_default();
+        let patch = next()?.unwrap_or_default();
+
+        Ok(Self::new(major, minor, patch))
+    }
+}
+
+impl std::fmt::Display for AmiVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
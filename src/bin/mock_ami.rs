@@ -0,0 +1,20 @@
+use asterisk_queue_handler_events::mock::{MockConfig, MockServer, Script};
+
+/// Mock AMI server that plays the demo script in a loop.
+/// Usage: cargo run --features mock --bin mock_ami [address], the credentials are USERNAME and SECRET
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    _ = dotenv::dotenv();
+
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:5038".to_string());
+    let user = std::env::var("USERNAME").unwrap_or_else(|_| "admin".to_string());
+    let secret = std::env::var("SECRET").unwrap_or_else(|_| "admin".to_string());
+
+    let config = MockConfig::new(user, secret).script(Script::demo("ventas").repeat(true));
+    let server = MockServer::bind(&addr, config).await?;
+    println!("Mock AMI listening on {}", server.local_addr()?);
+
+    server.run().await
+}
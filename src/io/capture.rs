@@ -0,0 +1,225 @@
+use std::{
+    future::Future,
+    io::{Error, ErrorKind},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    fs::File,
+    io::{AsyncRead, AsyncWriteExt, BufWriter, ReadBuf},
+    sync::{mpsc, oneshot},
+    time::Sleep,
+};
+
+/// Header of the capture files
+///
+/// Each chunk read is stored as: microseconds since the start (u64 LE), length (u32 LE), bytes
+const MAGIC: &[u8] = b"AMICAP\x01\n";
+
+/// Reader that saves every chunk read, with its timestamp, in a capture file.
+/// The file is written by a task, so the reads never wait for the disk
+pub struct RecordReader<R> {
+    inner: R,
+    chunks: Option<mpsc::UnboundedSender<Vec<u8>>>,
+    start: Instant,
+}
+
+/// End of the capture task, it resolves when the reader is dropped and the file is written,
+/// or with the error that stopped the capture (the reader keeps working without it)
+pub struct Capture(oneshot::Receiver<Error>);
+
+impl<R> RecordReader<R> {
+    /// Must be called inside a tokio runtime
+    pub fn new(inner: R, file: File) -> (Self, Capture) {
+        let (chunks, rx) = mpsc::unbounded_channel();
+        let (errors, capture) = oneshot::channel();
+        tokio::spawn(async move {
+            if let Err(er) = write_chunks(file, rx).await {
+                _ = errors.send(er);
+            }
+        });
+
+        let this = Self {
+            inner,
+            chunks: Some(chunks),
+            start: Instant::now(),
+        };
+        (this, Capture(capture))
+    }
+
+    pub async fn create(inner: R, path: impl AsRef<Path>) -> std::io::Result<(Self, Capture)> {
+        Ok(Self::new(inner, File::create(path).await?))
+    }
+
+    fn record(&mut self, data: &[u8]) {
+        let Some(chunks) = self.chunks.as_ref() else {
+            return;
+        };
+
+        let micros = self.start.elapsed().as_micros() as u64;
+        let mut chunk = Vec::with_capacity(12 + data.len());
+        chunk.extend_from_slice(&micros.to_le_bytes());
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+
+        // The task stopped, the error is reported by the Capture
+        if chunks.send(chunk).is_err() {
+            self.chunks = None;
+        }
+    }
+}
+
+/// The file is flushed when there are no more chunks waiting
+async fn write_chunks(file: File, mut chunks: mpsc::UnboundedReceiver<Vec<u8>>) -> std::io::Result<()> {
+    let mut file = BufWriter::new(file);
+    file.write_all(MAGIC).await?;
+
+    while let Some(chunk) = chunks.recv().await {
+        file.write_all(&chunk).await?;
+        while let Ok(chunk) = chunks.try_recv() {
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+    }
+    file.flush().await
+}
+
+impl Future for Capture {
+    type Output = std::io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match futures::ready!(Pin::new(&mut self.0).poll(cx)) {
+            Ok(er) => Poll::Ready(Err(er)),
+            // The task ended without errors
+            Err(_) => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for RecordReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let before = buf.filled().len();
+        futures::ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+
+        let data = &buf.filled()[before..];
+        if !data.is_empty() {
+            this.record(data);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Replay speed of a capture
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Speed {
+    /// Same timing as the capture
+    Original,
+
+    /// The waits are divided by the factor
+    Factor(f64),
+
+    /// Without waits
+    Max,
+}
+
+impl Speed {
+    fn scale(&self, time: Duration) -> Option<Duration> {
+        match self {
+            Speed::Original => Some(time),
+            Speed::Factor(factor) if *factor > 0.0 => Some(time.div_f64(*factor)),
+            Speed::Factor(_) | Speed::Max => None,
+        }
+    }
+}
+
+/// Reader that returns the chunks of a capture file
+pub struct ReplayReader {
+    chunks: Vec<(Duration, Vec<u8>)>,
+    index: usize,
+    offset: usize,
+    speed: Speed,
+    start: Option<tokio::time::Instant>,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl ReplayReader {
+    pub async fn open(path: impl AsRef<Path>, speed: Speed) -> std::io::Result<Self> {
+        Self::from_bytes(&tokio::fs::read(path).await?, speed)
+    }
+
+    pub fn from_bytes(data: &[u8], speed: Speed) -> std::io::Result<Self> {
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+
+        let mut data = data
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| invalid("It isn't a capture file"))?;
+        let mut chunks = Vec::new();
+
+        while !data.is_empty() {
+            if data.len() < 12 {
+                return Err(invalid("Truncated chunk header"));
+            }
+            let micros = u64::from_le_bytes(data[..8].try_into().unwrap());
+            let len = u32::from_le_bytes(data[8..12].try_into().unwrap()) as usize;
+            let chunk = data
+                .get(12..12 + len)
+                .ok_or_else(|| invalid("Truncated chunk"))?;
+            chunks.push((Duration::from_micros(micros), chunk.to_vec()));
+            data = &data[12 + len..];
+        }
+
+        Ok(Self {
+            chunks,
+            index: 0,
+            offset: 0,
+            speed,
+            start: None,
+            sleep: None,
+        })
+    }
+}
+
+impl AsyncRead for ReplayReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let start = *this.start.get_or_insert_with(tokio::time::Instant::now);
+
+        let Some((time, chunk)) = this.chunks.get(this.index) else {
+            return Poll::Ready(Ok(()));
+        };
+
+        // Wait only before the first byte of the chunk
+        if this.offset == 0
+            && let Some(time) = this.speed.scale(*time)
+        {
+            let sleep = this
+                .sleep
+                .get_or_insert_with(|| Box::pin(tokio::time::sleep_until(start + time)));
+            futures::ready!(sleep.as_mut().poll(cx));
+            this.sleep = None;
+        }
+
+        let n = buf.remaining().min(chunk.len() - this.offset);
+        buf.put_slice(&chunk[this.offset..this.offset + n]);
+        this.offset += n;
+
+        if this.offset == chunk.len() {
+            this.index += 1;
+            this.offset = 0;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,156 @@
+use bytes::{Bytes, BytesMut};
+use tokio_util::codec::Decoder;
+
+use crate::asterisk::{error::AmiError, event::MAX_FRAME_SIZE};
+
+/// Framing of the AMI: blocks of `Key: value` lines ended by a blank line.
+///
+/// The frames are slices of the read buffer (without the blank line), nothing is copied.
+/// The lines can end with CRLF or with a bare LF.
+/// The first frame is the banner (`Asterisk Call Manager/x.y.z`) when the stream starts with it,
+/// it's a single line without a blank line.
+/// A frame longer than the max frame size returns AmiError::FrameTooLarge,
+/// if the decoding continues the data until the next blank line is discarded
+#[derive(Debug)]
+pub struct AmiCodec {
+    /// Where the search of the blank line continues, so the data is scanned once
+    next_index: usize,
+    greeted: bool,
+    max_frame_size: usize,
+    skipping: bool,
+}
+
+impl Default for AmiCodec {
+    fn default() -> Self {
+        Self {
+            next_index: 0,
+            greeted: false,
+            max_frame_size: MAX_FRAME_SIZE,
+            skipping: false,
+        }
+    }
+}
+
+impl AmiCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_frame_size(mut self, limit: usize) -> Self {
+        self.max_frame_size = limit.max(1);
+        self
+    }
+
+    /// The codec must be used with a new stream, e.g. after a reconnection
+    pub fn reset(&mut self) {
+        self.next_index = 0;
+        self.greeted = false;
+        self.skipping = false;
+    }
+
+    fn too_large(&self) -> AmiError {
+        AmiError::FrameTooLarge {
+            limit: self.max_frame_size,
+        }
+    }
+}
+
+impl Decoder for AmiCodec {
+    type Item = Bytes;
+    type Error = AmiError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if !self.greeted {
+            let Some(pos) = memchr(b'\n', src) else {
+                if src.len() > self.max_frame_size {
+                    self.greeted = true;
+                    return self.decode(src);
+                }
+                return Ok(None);
+            };
+            self.greeted = true;
+
+            // A header means the stream has no banner
+            if !src[..pos].contains(&b':') {
+                let line = src.split_to(pos + 1).freeze();
+                return Ok(Some(line.slice(..trim_end(&line))));
+            }
+        }
+
+        // Blank lines between frames, when skipping they can be the end of the oversized frame
+        if self.next_index == 0 && !self.skipping {
+            let start = src.iter().take_while(|x| matches!(x, b'\r' | b'\n')).count();
+            _ = src.split_to(start);
+        }
+
+        while let Some(pos) = memchr(b'\n', &src[self.next_index..]) {
+            let line_end = self.next_index + pos + 1;
+            let blank = match src.get(line_end..) {
+                Some([b'\n', ..]) => Some(line_end + 1),
+                Some([b'\r', b'\n', ..]) => Some(line_end + 2),
+                // It can't be known yet if the next line is blank
+                Some([]) | Some([b'\r']) => {
+                    self.next_index = line_end - 1;
+                    return Ok(None);
+                }
+                _ => None,
+            };
+
+            if let Some(end) = blank {
+                self.next_index = 0;
+                let frame = src.split_to(end).freeze();
+
+                if std::mem::take(&mut self.skipping) {
+                    // End of the oversized frame, it was already reported
+                    return self.decode(src);
+                }
+                if line_end > self.max_frame_size {
+                    return Err(self.too_large());
+                }
+                return Ok(Some(frame.slice(..trim_end(&frame[..line_end]))));
+            }
+            self.next_index = line_end;
+        }
+
+        if self.skipping || src.len() > self.max_frame_size {
+            // The last bytes are kept, they can be the start of the blank line
+            _ = src.split_to(src.len().saturating_sub(3));
+            self.next_index = 0;
+
+            if !std::mem::replace(&mut self.skipping, true) {
+                return Err(self.too_large());
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if let Some(frame) = self.decode(src)? {
+            return Ok(Some(frame));
+        }
+
+        // The last frame without the blank line
+        self.next_index = 0;
+        if std::mem::take(&mut self.skipping) {
+            src.clear();
+            return Ok(None);
+        }
+        let end = trim_end(src);
+        let frame = src.split_to(src.len()).freeze();
+        Ok((end > 0).then(|| frame.slice(..end)))
+    }
+}
+
+fn memchr(byte: u8, data: &[u8]) -> Option<usize> {
+    data.iter().position(|x| *x == byte)
+}
+
+fn trim_end(data: &[u8]) -> usize {
+    data.len()
+        - data
+            .iter()
+            .rev()
+            .take_while(|x| x.is_ascii_whitespace())
+            .count()
+}
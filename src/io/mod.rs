@@ -1 +1,3 @@
+pub mod capture;
+pub mod codec;
 pub mod writer;
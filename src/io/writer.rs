@@ -19,6 +19,10 @@ impl<T: AsyncWrite> BufWriter<T> {
     pub fn to_write(&mut self, slice: &[u8]) {
         self.buffer.extend_from_slice(slice);
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
 }
 
 impl<T: AsyncWrite> AsyncWrite for BufWriter<T> {
@@ -42,33 +46,25 @@ impl<T: AsyncWrite> AsyncWrite for BufWriter<T> {
         self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Result<(), std::io::Error>> {
-
-        if self.buffer.is_empty() {
-            return std::task::Poll::Ready(Err(Error::new(std::io::ErrorKind::InvalidInput, "Buffer is empty")));
-        }
-
+        // With the buffer already written the inner flush can still be pending (e.g. TLS)
         let this = unsafe { Pin::get_unchecked_mut(self) };
-        let wr = unsafe {Pin::new_unchecked(&mut this.inner)};
-        let n = futures::ready!(wr.poll_write(cx, &mut this.buffer))?;
+        let mut wr = unsafe {Pin::new_unchecked(&mut this.inner)};
 
-        if n != this.buffer.len() {
+        while !this.buffer.is_empty() {
+            let n = futures::ready!(wr.as_mut().poll_write(cx, &this.buffer))?;
+            if n == 0 {
+                return std::task::Poll::Ready(Err(Error::from(std::io::ErrorKind::WriteZero)));
+            }
             this.buffer.advance(n);
-            std::task::Poll::Pending
-        } else {
-            this.buffer.clear();
-            std::task::Poll::Ready(Ok(()))
         }
+
+        wr.poll_flush(cx)
     }
 
     fn poll_shutdown(
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Result<(), std::io::Error>> {
-        
-        if self.buffer.is_empty() {
-            return std::task::Poll::Ready(Err(Error::new(std::io::ErrorKind::InvalidInput, "Buffer is empty")));
-        }
-
         futures::ready!(self.as_mut().poll_flush(cx))?;
 
         unsafe { Pin::map_unchecked_mut(self, |x| &mut x.inner) }.poll_shutdown(cx)
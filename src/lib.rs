@@ -0,0 +1,4 @@
+pub mod asterisk;
+pub mod io;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
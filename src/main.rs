@@ -1,16 +1,103 @@
-pub mod asterisk;
-pub mod io;
-
-use crate::asterisk::Alma;
+use asterisk_queue_handler_events::{
+    asterisk::{
+        Alma,
+        event::{Charset, EventHandler, LoginMode},
+        state::QueueState,
+        transport::{TlsConfig, Transport},
+    },
+    io::capture::{ReplayReader, Speed},
+};
+use futures::StreamExt;
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
     _ = dotenv::dotenv();
 
+    if let Ok(path) = std::env::var("REPLAY") {
+        return replay(path).await;
+    }
+
     let user = std::env::var("USERNAME").expect("Secret not found");
     let secret = std::env::var("SECRET").expect("Secret not found");
     let socket_ami = std::env::var("AMI").expect("Socket AMI not found");
+    let login_mode = match std::env::var("AUTH_TYPE").as_deref() {
+        Ok("md5" | "MD5") => LoginMode::Md5,
+        _ => LoginMode::Plain,
+    };
+
+    let transport = if std::env::var("AMI_TLS").is_ok_and(|x| x == "1" || x == "true") {
+        Transport::Tls(TlsConfig {
+            ca: std::env::var("AMI_TLS_CA").ok().map(Into::into),
+            cert: std::env::var("AMI_TLS_CERT").ok().map(Into::into),
+            key: std::env::var("AMI_TLS_KEY").ok().map(Into::into),
+            pinned: std::env::var("AMI_TLS_PINNED").ok().map(Into::into),
+            server_name: std::env::var("AMI_TLS_SERVER_NAME").ok(),
+        })
+    } else {
+        Transport::Tcp
+    };
+
+    let charset = match std::env::var("AMI_CHARSET").as_deref() {
+        Ok("latin1" | "iso-8859-1" | "ISO-8859-1") => Charset::Latin1,
+        _ => Charset::Utf8Lossy,
+    };
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+    let mut alma = Alma::new(socket_ami, user, secret)
+        .login_mode(login_mode)
+        .transport(transport)
+        .charset(charset)
+        .call_events(std::env::var("AMI_CALL_EVENTS").is_ok_and(|x| x == "1" || x == "true"));
+    if let Ok(path) = std::env::var("AMI_RECORD") {
+        alma = alma.record(path);
+    }
+    tokio::spawn(alma.run(tx));
+
+    while let Some(event) = rx.recv().await {
+        println!("{event:?}");
+    }
+
+    Ok(())
+}
+
+/// Feed a capture file to the parser, REPLAY_SPEED: max, a factor (e.g. 10) or the original speed
+async fn replay(path: String) -> std::io::Result<()> {
+    let speed = match std::env::var("REPLAY_SPEED").as_deref() {
+        Ok("max") => Speed::Max,
+        Ok(factor) => factor.parse().map(Speed::Factor).unwrap_or(Speed::Original),
+        Err(_) => Speed::Original,
+    };
+    let login_mode = match std::env::var("AUTH_TYPE").as_deref() {
+        Ok("md5" | "MD5") => LoginMode::Md5,
+        _ => LoginMode::Plain,
+    };
+
+    // The actions are discarded, the ActionIDs are the same of the recorded session
+    let mut handler = EventHandler::from_split(
+        ReplayReader::open(path, speed).await?,
+        tokio::io::sink(),
+        String::new(),
+        String::new(),
+    )
+    .login_mode(login_mode);
+    let state = QueueState::new();
+
+    while let Some(msg) = handler.next().await {
+        match msg {
+            Ok(msg) => {
+                if let Err(er) = state.apply(&msg) {
+                    println!("{er}");
+                }
+                println!("{msg:?}");
+            }
+            Err(er) => println!("{er}"),
+        }
+    }
+
+    for queue in state.queues() {
+        println!("{queue:?}");
+    }
 
-    Alma::run(socket_ami, user, secret).await;
     Ok(())
 }
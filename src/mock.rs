@@ -0,0 +1,642 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use rand::Rng;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream, tcp::OwnedReadHalf},
+    sync::mpsc,
+    task::JoinHandle,
+    time::Instant,
+};
+
+/// AMI frame, the headers keep the order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Frame(pub Vec<(String, String)>);
+
+impl Frame {
+    pub fn event(name: &str) -> Self {
+        Self::default().header("Event", name)
+    }
+
+    pub fn response(result: &str) -> Self {
+        Self::default().header("Response", result)
+    }
+
+    pub fn header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.0.push((key.into(), value.into()));
+        self
+    }
+
+    /// The keys are case insensitive
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn with_action_id(self, action_id: Option<&str>) -> Self {
+        match action_id {
+            Some(id) => self.header("ActionID", id),
+            None => self,
+        }
+    }
+
+    fn to_wire(&self) -> String {
+        let mut data = String::new();
+        for (key, value) in &self.0 {
+            data.push_str(key);
+            data.push_str(": ");
+            data.push_str(value);
+            data.push_str("\r\n");
+        }
+        data.push_str("\r\n");
+        data
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Step {
+    Wait(Duration),
+    Send(Frame),
+    /// The server closes the connection, e.g. to test the reconnection
+    Close,
+}
+
+/// Events sent once the client subscribes with Action: Events
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    pub steps: Vec<Step>,
+    pub repeat: bool,
+}
+
+impl Script {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Play the script again when it ends
+    pub fn repeat(mut self, repeat: bool) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn wait(mut self, wait: Duration) -> Self {
+        self.steps.push(Step::Wait(wait));
+        self
+    }
+
+    pub fn send(mut self, frame: Frame) -> Self {
+        self.steps.push(Step::Send(frame));
+        self
+    }
+
+    pub fn close(mut self) -> Self {
+        self.steps.push(Step::Close);
+        self
+    }
+
+    pub fn caller_join(self, queue: &str, unique_id: &str, number: &str, position: u16) -> Self {
+        self.send(
+            Frame::event("QueueCallerJoin")
+                .header("Queue", queue)
+                .header("Position", position.to_string())
+                .header("Count", position.to_string())
+                .header("CallerIDNum", number)
+                .header("CallerIDName", format!("Caller {number}"))
+                .header("Uniqueid", unique_id),
+        )
+    }
+
+    pub fn caller_leave(self, queue: &str, unique_id: &str, position: u16) -> Self {
+        self.send(
+            Frame::event("QueueCallerLeave")
+                .header("Queue", queue)
+                .header("Position", position.to_string())
+                .header("Uniqueid", unique_id),
+        )
+    }
+
+    pub fn caller_abandon(self, queue: &str, unique_id: &str, position: u16, hold_time: u64) -> Self {
+        self.send(
+            Frame::event("QueueCallerAbandon")
+                .header("Queue", queue)
+                .header("Position", position.to_string())
+                .header("OriginalPosition", position.to_string())
+                .header("HoldTime", hold_time.to_string())
+                .header("Uniqueid", unique_id),
+        )
+        .caller_leave(queue, unique_id, position)
+    }
+
+    pub fn agent_called(self, queue: &str, interface: &str, unique_id: &str) -> Self {
+        self.send(
+            Frame::event("AgentCalled")
+                .header("Queue", queue)
+                .header("Interface", interface)
+                .header("MemberName", interface)
+                .header("Uniqueid", unique_id),
+        )
+    }
+
+    pub fn agent_ring_no_answer(self, queue: &str, interface: &str, unique_id: &str, ring_time: u64) -> Self {
+        self.send(
+            Frame::event("AgentRingNoAnswer")
+                .header("Queue", queue)
+                .header("Interface", interface)
+                .header("MemberName", interface)
+                .header("RingTime", ring_time.to_string())
+                .header("Uniqueid", unique_id),
+        )
+    }
+
+    pub fn agent_connect(self, queue: &str, interface: &str, unique_id: &str, hold_time: u64) -> Self {
+        self.send(
+            Frame::event("AgentConnect")
+                .header("Queue", queue)
+                .header("Interface", interface)
+                .header("MemberName", interface)
+                .header("HoldTime", hold_time.to_string())
+                .header("RingTime", "2")
+                .header("Uniqueid", unique_id),
+        )
+    }
+
+    pub fn agent_complete(self, queue: &str, interface: &str, unique_id: &str, hold_time: u64, talk_time: u64) -> Self {
+        self.send(
+            Frame::event("AgentComplete")
+                .header("Queue", queue)
+                .header("Interface", interface)
+                .header("MemberName", interface)
+                .header("HoldTime", hold_time.to_string())
+                .header("TalkTime", talk_time.to_string())
+                .header("Reason", "caller")
+                .header("Uniqueid", unique_id),
+        )
+    }
+
+    pub fn agent_login(self, agent: &str, channel: &str) -> Self {
+        self.send(
+            Frame::event("AgentLogin")
+                .header("Channel", channel)
+                .header("ChannelStateDesc", "Up")
+                .header("Uniqueid", "1700000100.1")
+                .header("Linkedid", "1700000100.1")
+                .header("Agent", agent),
+        )
+    }
+
+    pub fn agent_logoff(self, agent: &str, channel: &str, log_in_time: u64) -> Self {
+        self.send(
+            Frame::event("AgentLogoff")
+                .header("Channel", channel)
+                .header("ChannelStateDesc", "Up")
+                .header("Uniqueid", "1700000100.1")
+                .header("Linkedid", "1700000100.1")
+                .header("Agent", agent)
+                .header("Logintime", log_in_time.to_string()),
+        )
+    }
+
+    /// An agent logging in, callers joining, agents ringing and answering, one abandon
+    pub fn demo(queue: &str) -> Self {
+        let second = Duration::from_secs(1);
+        Self::new()
+            .agent_login("100", "PJSIP/100-00000001")
+            .caller_join(queue, "1700000000.1", "1001", 1)
+            .wait(second)
+            .agent_called(queue, "Local/100@from-queue/n", "1700000000.1")
+            .caller_join(queue, "1700000000.2", "1002", 2)
+            .wait(second * 2)
+            .agent_connect(queue, "Local/100@from-queue/n", "1700000000.1", 3)
+            .caller_leave(queue, "1700000000.1", 1)
+            .caller_join(queue, "1700000000.3", "1003", 2)
+            .wait(second)
+            .agent_called(queue, "Local/101@from-queue/n", "1700000000.2")
+            .wait(second * 3)
+            .agent_ring_no_answer(queue, "Local/101@from-queue/n", "1700000000.2", 3)
+            .caller_abandon(queue, "1700000000.3", 2, 5)
+            .wait(second * 2)
+            .agent_complete(queue, "Local/100@from-queue/n", "1700000000.1", 3, 5)
+            .agent_called(queue, "Local/100@from-queue/n", "1700000000.2")
+            .wait(second)
+            .agent_connect(queue, "Local/100@from-queue/n", "1700000000.2", 8)
+            .caller_leave(queue, "1700000000.2", 1)
+            .wait(second * 4)
+            .agent_complete(queue, "Local/100@from-queue/n", "1700000000.2", 8, 4)
+            .wait(second * 2)
+    }
+}
+
+/// Behaviour of the mock server
+///
+/// snapshot: events answered to Action: QueueStatus, without ActionID
+/// script: events played after Action: Events
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    pub username: String,
+    pub secret: String,
+    pub version: String,
+    pub snapshot: Vec<Frame>,
+    pub script: Script,
+}
+
+impl MockConfig {
+    pub fn new(username: impl Into<String>, secret: impl Into<String>) -> Self {
+        Self {
+            username: username.into(),
+            secret: secret.into(),
+            version: "7.0.3".to_string(),
+            snapshot: Self::default_snapshot("ventas"),
+            script: Script::default(),
+        }
+    }
+
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    pub fn snapshot(mut self, snapshot: Vec<Frame>) -> Self {
+        self.snapshot = snapshot;
+        self
+    }
+
+    pub fn script(mut self, script: Script) -> Self {
+        self.script = script;
+        self
+    }
+
+    /// One queue with two members and nobody waiting
+    pub fn default_snapshot(queue: &str) -> Vec<Frame> {
+        // Like FreePBX: a Local channel with the device as the state interface
+        let member = |extension: &str, name: &str| {
+            Frame::event("QueueMember")
+                .header("Queue", queue)
+                .header("Name", name)
+                .header("Location", format!("Local/{extension}@from-queue/n"))
+                .header("StateInterface", format!("PJSIP/{extension}"))
+                .header("Membership", "static")
+                .header("Penalty", "0")
+                .header("CallsTaken", "0")
+                .header("LastCall", "0")
+                .header("LastPause", "0")
+                .header("InCall", "0")
+                .header("Status", "1")
+                .header("Paused", "0")
+                .header("PausedReason", "")
+        };
+
+        vec![
+            Frame::event("QueueParams")
+                .header("Queue", queue)
+                .header("Max", "0")
+                .header("Strategy", "ringall")
+                .header("Calls", "0")
+                .header("Holdtime", "0")
+                .header("TalkTime", "0")
+                .header("Completed", "0")
+                .header("Abandoned", "0")
+                .header("ServiceLevel", "60")
+                .header("ServicelevelPerf", "0.0")
+                .header("ServicelevelPerf2", "0.0")
+                .header("Weight", "0"),
+            member("100", "Agent 100"),
+            member("101", "Agent 101"),
+        ]
+    }
+}
+
+/// Local stand-in for Asterisk, for the tests and to demo the dashboards
+pub struct MockServer {
+    listener: TcpListener,
+    config: Arc<MockConfig>,
+    received_tx: mpsc::UnboundedSender<Frame>,
+    received_rx: Option<mpsc::UnboundedReceiver<Frame>>,
+    errors_tx: mpsc::UnboundedSender<std::io::Error>,
+    errors_rx: Option<mpsc::UnboundedReceiver<std::io::Error>>,
+}
+
+impl MockServer {
+    pub async fn bind(addr: &str, config: MockConfig) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let (received_tx, received_rx) = mpsc::unbounded_channel();
+        let (errors_tx, errors_rx) = mpsc::unbounded_channel();
+        Ok(Self {
+            listener,
+            config: Arc::new(config),
+            received_tx,
+            received_rx: Some(received_rx),
+            errors_tx,
+            errors_rx: Some(errors_rx),
+        })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Actions received by every connection, it can be taken once
+    pub fn received(&mut self) -> Option<mpsc::UnboundedReceiver<Frame>> {
+        self.received_rx.take()
+    }
+
+    /// Errors that ended a connection, it can be taken once.
+    /// When it isn't taken they are printed to stderr
+    pub fn errors(&mut self) -> Option<mpsc::UnboundedReceiver<std::io::Error>> {
+        self.errors_rx.take()
+    }
+
+    pub async fn run(self) -> std::io::Result<()> {
+        let errors = self.errors_rx.is_none().then_some(self.errors_tx);
+        loop {
+            let (stream, _) = self.listener.accept().await?;
+            let config = self.config.clone();
+            let received = self.received_tx.clone();
+            let errors = errors.clone();
+            tokio::spawn(async move {
+                if let Err(er) = Session::new(config, received).run(stream).await {
+                    match errors {
+                        Some(errors) => _ = errors.send(er),
+                        None => eprintln!("Mock session: {er}"),
+                    }
+                }
+            });
+        }
+    }
+
+    pub fn spawn(self) -> JoinHandle<std::io::Result<()>> {
+        tokio::spawn(self.run())
+    }
+}
+
+struct Session {
+    config: Arc<MockConfig>,
+    received: mpsc::UnboundedSender<Frame>,
+    logged_in: bool,
+    challenge: Option<String>,
+    step: usize,
+    wake: Option<Instant>,
+}
+
+impl Session {
+    fn new(config: Arc<MockConfig>, received: mpsc::UnboundedSender<Frame>) -> Self {
+        Self {
+            config,
+            received,
+            logged_in: false,
+            challenge: None,
+            step: 0,
+            wake: None,
+        }
+    }
+
+    async fn run(mut self, stream: TcpStream) -> std::io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        writer
+            .write_all(format!("Asterisk Call Manager/{}\r\n", self.config.version).as_bytes())
+            .await?;
+
+        // The frames are read in other task, read_line can't be cancelled by select!
+        let (frames_tx, mut frames) = mpsc::unbounded_channel();
+        let mut reader = tokio::spawn(read_frames(reader, frames_tx));
+
+        let result = loop {
+            let wake = self.wake;
+            tokio::select! {
+                frame = frames.recv() => {
+                    let Some(frame) = frame else {
+                        // The client closed, or the socket failed
+                        break (&mut reader).await.unwrap_or(Ok(()));
+                    };
+                    _ = self.received.send(frame.clone());
+
+                    let (replies, close) = self.answer(&frame);
+                    let data = replies.iter().map(Frame::to_wire).collect::<String>();
+                    if let Err(er) = writer.write_all(data.as_bytes()).await {
+                        break Err(er);
+                    }
+                    if close {
+                        break Ok(());
+                    }
+                }
+                _ = tokio::time::sleep_until(wake.unwrap_or_else(Instant::now)), if wake.is_some() => {
+                    let (frames, close) = self.play();
+                    let data = frames.iter().map(Frame::to_wire).collect::<String>();
+                    if let Err(er) = writer.write_all(data.as_bytes()).await {
+                        break Err(er);
+                    }
+                    if close {
+                        break Ok(());
+                    }
+                }
+            }
+        };
+
+        reader.abort();
+        result
+    }
+
+    /// Frames of the script until the next wait and if the connection must be closed
+    fn play(&mut self) -> (Vec<Frame>, bool) {
+        let steps = &self.config.script.steps;
+        let mut frames = Vec::new();
+
+        while let Some(step) = steps.get(self.step) {
+            self.step += 1;
+            match step {
+                Step::Send(frame) => frames.push(frame.clone()),
+                Step::Wait(wait) => {
+                    self.wake = Some(Instant::now() + *wait);
+                    return (frames, false);
+                }
+                Step::Close => {
+                    self.wake = None;
+                    return (frames, true);
+                }
+            }
+        }
+
+        let has_wait = steps.iter().any(|x| matches!(x, Step::Wait(_)));
+        if self.config.script.repeat && has_wait {
+            self.step = 0;
+            self.wake = Some(Instant::now());
+        } else {
+            self.wake = None;
+        }
+        (frames, false)
+    }
+
+    /// Frames to send and if the connection must be closed
+    fn answer(&mut self, frame: &Frame) -> (Vec<Frame>, bool) {
+        let action_id = frame.get("ActionID");
+        let action = frame.get("Action").unwrap_or_default().to_ascii_lowercase();
+
+        let reply = |frame: Frame| frame.with_action_id(action_id);
+        let error = |message: &str| reply(Frame::response("Error").header("Message", message));
+        let success = |message: &str| reply(Frame::response("Success").header("Message", message));
+
+        if !self.logged_in && !matches!(action.as_str(), "login" | "challenge" | "logoff") {
+            return (vec![error("Permission denied")], false);
+        }
+
+        match action.as_str() {
+            "challenge" => {
+                let challenge = rand::rng().random_range(100_000_000..1_000_000_000u32).to_string();
+                self.challenge = Some(challenge.clone());
+                (vec![reply(Frame::response("Success").header("Challenge", challenge))], false)
+            }
+            "login" => {
+                let secret_ok = match (frame.get("AuthType"), frame.get("Key"), &self.challenge) {
+                    (Some(auth), Some(key), Some(challenge)) if auth.eq_ignore_ascii_case("md5") => {
+                        let expected = md5::compute(format!("{challenge}{}", self.config.secret));
+                        format!("{expected:x}") == key.to_ascii_lowercase()
+                    }
+                    _ => frame.get("Secret") == Some(self.config.secret.as_str()),
+                };
+
+                if secret_ok && frame.get("Username") == Some(self.config.username.as_str()) {
+                    self.logged_in = true;
+                    (vec![success("Authentication accepted")], false)
+                } else {
+                    (vec![error("Authentication failed")], true)
+                }
+            }
+            "events" => {
+                let off = frame
+                    .get("EventMask")
+                    .is_some_and(|x| x.eq_ignore_ascii_case("off"));
+                if !off && self.wake.is_none() {
+                    self.step = 0;
+                    self.wake = Some(Instant::now());
+                }
+                let events = if off { "Off" } else { "On" };
+                (vec![reply(Frame::response("Success").header("Events", events))], false)
+            }
+            "queuestatus" => {
+                let queue = frame.get("Queue");
+                let mut frames = vec![reply(
+                    Frame::response("Success")
+                        .header("EventList", "start")
+                        .header("Message", "Queue status will follow"),
+                )];
+                frames.extend(
+                    self.config
+                        .snapshot
+                        .iter()
+                        .filter(|x| queue.is_none() || x.get("Queue") == queue)
+                        .map(|x| x.clone().with_action_id(action_id)),
+                );
+                let items = frames.len() - 1;
+                frames.push(reply(
+                    Frame::event("QueueStatusComplete")
+                        .header("EventList", "Complete")
+                        .header("ListItems", items.to_string()),
+                ));
+                (frames, false)
+            }
+            "queuesummary" => {
+                let queue = frame.get("Queue");
+                let snapshot = &self.config.snapshot;
+                let mut frames = vec![reply(
+                    Frame::response("Success")
+                        .header("EventList", "start")
+                        .header("Message", "Queue summary will follow"),
+                )];
+                frames.extend(
+                    snapshot
+                        .iter()
+                        .filter(|x| x.get("Event") == Some("QueueParams"))
+                        .filter(|x| queue.is_none() || x.get("Queue") == queue)
+                        .map(|params| {
+                            let name = params.get("Queue").unwrap_or_default();
+                            let members = snapshot.iter().filter(|x| {
+                                x.get("Event") == Some("QueueMember") && x.get("Queue") == Some(name)
+                            });
+                            let logged_in = members.clone().count();
+                            let available = members
+                                .filter(|x| x.get("Status") == Some("1") && x.get("Paused") == Some("0"))
+                                .count();
+
+                            reply(
+                                Frame::event("QueueSummary")
+                                    .header("Queue", name)
+                                    .header("LoggedIn", logged_in.to_string())
+                                    .header("Available", available.to_string())
+                                    .header("Callers", params.get("Calls").unwrap_or("0"))
+                                    .header("HoldTime", params.get("Holdtime").unwrap_or("0"))
+                                    .header("TalkTime", params.get("TalkTime").unwrap_or("0"))
+                                    .header("LongestHoldTime", "0"),
+                            )
+                        }),
+                );
+                let items = frames.len() - 1;
+                frames.push(reply(
+                    Frame::event("QueueSummaryComplete")
+                        .header("EventList", "Complete")
+                        .header("ListItems", items.to_string()),
+                ));
+                (frames, false)
+            }
+            "queuepause" => {
+                let paused = frame.get("Paused").unwrap_or("false");
+                let paused = if matches!(paused.to_ascii_lowercase().as_str(), "true" | "1" | "yes") {
+                    "1"
+                } else {
+                    "0"
+                };
+                let event = Frame::event("QueueMemberPause")
+                    .header("Queue", frame.get("Queue").unwrap_or("ventas"))
+                    .header("Interface", frame.get("Interface").unwrap_or_default())
+                    .header("Paused", paused)
+                    .header("PausedReason", frame.get("Reason").unwrap_or_default());
+                (vec![success("Interface paused successfully"), event], false)
+            }
+            "queueadd" | "queueremove" => {
+                let (message, event) = if action == "queueadd" {
+                    ("Added interface to queue", "QueueMemberAdded")
+                } else {
+                    ("Removed interface from queue", "QueueMemberRemoved")
+                };
+                let event = Frame::event(event)
+                    .header("Queue", frame.get("Queue").unwrap_or_default())
+                    .header("Interface", frame.get("Interface").unwrap_or_default())
+                    .header("MemberName", frame.get("MemberName").unwrap_or_default())
+                    .header("Status", "1")
+                    .header("Paused", "0");
+                (vec![success(message), event], false)
+            }
+            "ping" => (vec![reply(Frame::response("Success").header("Ping", "Pong"))], false),
+            "logoff" => (
+                vec![reply(Frame::response("Goodbye").header("Message", "Thanks for all the fish."))],
+                true,
+            ),
+            _ => (vec![error("Invalid/unknown command")], false),
+        }
+    }
+}
+
+async fn read_frames(reader: OwnedReadHalf, tx: mpsc::UnboundedSender<Frame>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    let mut frame = Frame::default();
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(());
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            if !frame.0.is_empty() && tx.send(std::mem::take(&mut frame)).is_err() {
+                return Ok(());
+            }
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once(':') {
+            frame = frame.header(key.trim(), value.trim());
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use asterisk_queue_handler_events::asterisk::{
+    action::{EventList, FollowUp, FollowUpEvent, PendingActions},
+    client::AmiClient,
+    entities::ResponseAmyType,
+    error::AmiError,
+    event::AmiMessage,
+    queue_action::QueuePause,
+};
+use tokio::sync::oneshot;
+
+fn paused() -> FollowUp {
+    FollowUp {
+        event: FollowUpEvent::MemberPaused,
+        queue: Some("ventas".to_string()),
+        interface: "PJSIP/100".to_string(),
+    }
+}
+
+fn message(frame: &str) -> AmiMessage {
+    AmiMessage::try_from(frame).unwrap()
+}
+
+#[test]
+fn timed_out_action_is_removed() {
+    let mut pending = PendingActions::default();
+
+    // The caller stopped waiting before the follow-up event
+    let (tx, rx) = oneshot::channel();
+    let old = pending.register(ResponseAmyType::Action, Some(tx), Some(paused()));
+    let response = message(&format!("Response: Success\r\nActionID: {old}"));
+    assert!(pending.dispatch(&old, response, EventList::None).is_none());
+    drop(rx);
+
+    let (tx, mut rx) = oneshot::channel();
+    let new = pending.register(ResponseAmyType::Action, Some(tx), Some(paused()));
+
+    // The old ActionID is unknown now, a late message goes to the stream
+    let late = message(&format!("Response: Success\r\nActionID: {old}"));
+    assert!(pending.dispatch(&old, late, EventList::None).is_some());
+
+    let response = message(&format!("Response: Success\r\nActionID: {new}"));
+    assert!(pending.dispatch(&new, response, EventList::None).is_none());
+    pending.follow_up(&message(
+        "Event: QueueMemberPause\r\nQueue: ventas\r\nInterface: PJSIP/100\r\nPaused: 1",
+    ));
+
+    let response = rx.try_recv().unwrap();
+    assert_eq!(response.events.len(), 1);
+}
+
+#[tokio::test]
+async fn line_break_in_a_header_is_rejected() {
+    let (client, mut requests) = AmiClient::channel();
+
+    let pause = QueuePause::new("PJSIP/100", true).reason("lunch\r\n\r\nAction: Originate");
+    let result = client.send_action(pause).await;
+    assert!(
+        matches!(&result, Err(AmiError::InvalidHeader { key, .. }) if key == "Reason"),
+        "{result:?}"
+    );
+
+    let pause = QueuePause::new("PJSIP/100\nAction: Logoff", true);
+    assert!(matches!(
+        client.send_action(pause).await,
+        Err(AmiError::InvalidHeader { .. })
+    ));
+
+    // Nothing was queued to be written
+    assert!(requests.try_recv().is_err());
+}
@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+use asterisk_queue_handler_events::asterisk::connection::Backoff;
+
+#[test]
+fn out_of_range_values_are_clamped() {
+    let max = Duration::from_millis(100);
+    for (multiplier, jitter) in [(-3.0, 1.5), (0.5, -0.5), (f64::NAN, f64::NAN), (1e300, 1.0)] {
+        let mut backoff = Backoff::new(Duration::from_millis(10), max)
+            .multiplier(multiplier)
+            .jitter(jitter);
+        for _ in 0..50 {
+            assert!(backoff.next_delay() <= max);
+        }
+    }
+}
+
+#[test]
+fn delays_without_jitter() {
+    let mut backoff = Backoff::new(Duration::from_millis(10), Duration::from_millis(50))
+        .multiplier(3.0)
+        .jitter(0.0);
+    let delays: Vec<_> = (0..4).map(|_| backoff.next_delay().as_millis()).collect();
+    assert_eq!(delays, [10, 30, 50, 50]);
+
+    backoff.reset();
+    assert_eq!(backoff.next_delay(), Duration::from_millis(10));
+}
@@ -0,0 +1,158 @@
+use std::{thread::sleep, time::Duration};
+
+use asterisk_queue_handler_events::asterisk::{
+    call::{ACTIVE_CALLS, Calls, TransferKind},
+    event::AmiMessage,
+};
+
+fn message(frame: &str) -> AmiMessage {
+    AmiMessage::try_from(frame).unwrap()
+}
+
+const CONNECT: &str = "Event: AgentConnect\r\nQueue: ventas\r\nInterface: PJSIP/100\r\n\
+    Uniqueid: 1.1\r\nDestUniqueid: 1.2\r\nHoldTime: 5\r\nRingTime: 2";
+
+const COMPLETE: &str = "Event: AgentComplete\r\nQueue: ventas\r\nInterface: PJSIP/100\r\n\
+    Uniqueid: 1.1\r\nDestUniqueid: 1.2\r\nHoldTime: 5\r\nTalkTime: 30\r\nReason: transfer";
+
+const BLIND_TRANSFER: &str = "Event: BlindTransfer\r\nResult: Success\r\n\
+    TransfererChannel: PJSIP/100-00000002\r\nTransfererUniqueid: 1.2\r\n\
+    IsExternal: No\r\nContext: ventas\r\nExtension: 200";
+
+fn apply_all(order: &[&str]) -> Calls {
+    let mut calls = Calls::default();
+    for frame in order {
+        calls.apply(&message(frame));
+    }
+    calls
+}
+
+#[test]
+fn transfer_before_agent_complete() {
+    let calls = apply_all(&[CONNECT, BLIND_TRANSFER, COMPLETE]);
+    let call = calls.agent_call("1.1").unwrap();
+    assert!(call.completed_at.is_some());
+    assert!(call.transfer.as_ref().is_some_and(|x| x.is_success()));
+}
+
+#[test]
+fn transfer_after_agent_complete() {
+    let calls = apply_all(&[CONNECT, COMPLETE, BLIND_TRANSFER]);
+    assert_eq!(calls.agent_calls().count(), 0);
+
+    let call = calls.agent_call("1.1").unwrap();
+    assert_eq!(call.reason, "transfer");
+    let transfer = call.transfer.as_ref().unwrap();
+    assert!(transfer.is_success());
+    assert!(matches!(&transfer.kind, TransferKind::Blind { extension, .. } if extension == "200"));
+}
+
+#[test]
+fn transfer_goes_to_the_last_completed_call() {
+    // The same caller answered twice, the transfer is of the second agent
+    let second_connect = CONNECT.replace("1.2", "1.3");
+    let second_complete = COMPLETE.replace("1.2", "1.3");
+    let transfer = BLIND_TRANSFER.replace("1.2", "1.3");
+    let calls = apply_all(&[
+        CONNECT,
+        COMPLETE,
+        &second_connect,
+        &second_complete,
+        &transfer,
+    ]);
+
+    let completed: Vec<_> = calls.completed_agent_calls().collect();
+    assert_eq!(completed.len(), 2);
+    assert!(completed[0].transfer.is_none());
+    assert!(completed[1].transfer.is_some());
+}
+
+/// Channel event of the call 1.1
+fn channel(event: &str, unique_id: &str, extra: &str) -> AmiMessage {
+    message(&format!(
+        "Event: {event}\r\nChannel: PJSIP/{unique_id}\r\nChannelStateDesc: Up\r\n\
+        Uniqueid: {unique_id}\r\nLinkedid: 1.1\r\n{extra}"
+    ))
+}
+
+fn bridge(event: &str, unique_id: &str) -> AmiMessage {
+    channel(event, unique_id, "BridgeUniqueid: b1\r\nBridgeType: basic")
+}
+
+#[test]
+fn talk_time_of_each_leg_after_a_transfer() {
+    let mut calls = Calls::default();
+    // Caller 1.1 talks with the agent 1.2, who transfers to 1.3
+    for unique_id in ["1.1", "1.2"] {
+        calls.apply(&channel("Newchannel", unique_id, ""));
+    }
+    calls.apply(&message("Event: BridgeCreate\r\nBridgeUniqueid: b1"));
+    calls.apply(&bridge("BridgeEnter", "1.1"));
+    calls.apply(&bridge("BridgeEnter", "1.2"));
+    sleep(Duration::from_millis(20));
+
+    calls.apply(&channel("Newchannel", "1.3", ""));
+    calls.apply(&bridge("BridgeLeave", "1.2"));
+    calls.apply(&bridge("BridgeEnter", "1.3"));
+    let agent = calls.get("1.1").unwrap().talk_time_with("1.1", "1.2");
+    sleep(Duration::from_millis(40));
+
+    calls.apply(&bridge("BridgeLeave", "1.1"));
+    calls.apply(&bridge("BridgeLeave", "1.3"));
+    calls.apply(&message("Event: BridgeDestroy\r\nBridgeUniqueid: b1"));
+
+    let call = calls.get("1.1").unwrap();
+    assert_eq!(call.talk.len(), 2, "{:?}", call.talk);
+    assert!(call.talk.iter().all(|x| x.ended_at.is_some()));
+
+    // The leg of the agent ended with the transfer
+    assert_eq!(call.talk_time_with("1.1", "1.2"), agent);
+    assert!(agent >= Duration::from_millis(20));
+    assert!(call.talk_time_with("1.1", "1.3") >= Duration::from_millis(40));
+    assert_eq!(call.talk_time_with("1.2", "1.3"), Duration::ZERO);
+    assert_eq!(
+        call.talk_time("1.1"),
+        agent + call.talk_time_with("1.1", "1.3")
+    );
+}
+
+fn new_channel(unique_id: &str) -> AmiMessage {
+    message(&format!(
+        "Event: Newchannel\r\nChannel: PJSIP/200-{unique_id}\r\nChannelStateDesc: Ring\r\n\
+        Uniqueid: {unique_id}\r\nLinkedid: {unique_id}"
+    ))
+}
+
+#[test]
+fn oldest_call_is_discarded_when_full() {
+    let mut calls = Calls::default();
+    calls.apply(&new_channel("0.1"));
+    sleep(Duration::from_millis(1));
+    for i in 1..=ACTIVE_CALLS {
+        calls.apply(&new_channel(&format!("{i}.1")));
+    }
+
+    assert_eq!(calls.active().count(), ACTIVE_CALLS);
+    assert!(calls.get("0.1").is_none());
+    assert!(calls.get(&format!("{ACTIVE_CALLS}.1")).is_some());
+}
+
+#[test]
+fn clear_active_keeps_the_completed_calls() {
+    let mut calls = apply_all(&[CONNECT, COMPLETE]);
+    calls.apply(&new_channel("2.1"));
+    calls.apply(&message(
+        &CONNECT.replace("1.1", "2.1").replace("1.2", "2.2"),
+    ));
+
+    calls.clear_active();
+    assert_eq!(calls.active().count(), 0);
+    assert_eq!(calls.agent_calls().count(), 0);
+    assert_eq!(calls.completed_agent_calls().count(), 1);
+
+    // The AgentComplete after the reconnection is still recorded
+    calls.apply(&message(
+        &COMPLETE.replace("1.1", "2.1").replace("1.2", "2.2"),
+    ));
+    assert_eq!(calls.completed_agent_calls().count(), 2);
+}
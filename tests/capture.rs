@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use asterisk_queue_handler_events::{
+    asterisk::{
+        Alma, AlmaEvent,
+        connection::ConnectionState,
+        error::AmiError,
+        event::{AmiMessage, EventHandler},
+    },
+    io::capture::{RecordReader, ReplayReader, Speed},
+    mock::{MockConfig, MockServer, Script},
+};
+use futures::StreamExt;
+use tempfile::TempDir;
+use tokio::{net::TcpStream, sync::mpsc};
+
+const USER: &str = "admin";
+const SECRET: &str = "secret";
+
+async fn mock(script: Script) -> String {
+    let server = MockServer::bind("127.0.0.1:0", MockConfig::new(USER, SECRET).script(script))
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap().to_string();
+    server.spawn();
+    addr
+}
+
+fn is_last(msg: &Result<AmiMessage, AmiError>) -> bool {
+    matches!(msg, Ok(AmiMessage::CallerLeave(caller)) if caller.callet_unique_id == "1.1")
+}
+
+#[tokio::test]
+async fn replay_of_a_recorded_session() {
+    let script = Script::new()
+        .wait(Duration::from_millis(50))
+        .caller_join("ventas", "1.1", "1001", 1)
+        .agent_called("ventas", "PJSIP/100", "1.1")
+        .wait(Duration::from_millis(50))
+        .agent_connect("ventas", "PJSIP/100", "1.1", 3)
+        .caller_leave("ventas", "1.1", 1);
+    let addr = mock(script).await;
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("session.amicap");
+
+    let (reader, writer) = TcpStream::connect(&addr).await.unwrap().into_split();
+    let (reader, capture) = RecordReader::create(reader, &path).await.unwrap();
+    let mut handler =
+        EventHandler::from_split(reader, writer, USER.to_string(), SECRET.to_string());
+
+    let mut recorded = Vec::new();
+    while let Some(msg) = handler.next().await {
+        let last = is_last(&msg);
+        recorded.push(format!("{msg:?}"));
+        if last {
+            break;
+        }
+    }
+    // The file is complete once the reader is dropped
+    drop(handler);
+    tokio::time::timeout(Duration::from_secs(5), capture)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let replay = ReplayReader::open(&path, Speed::Max).await.unwrap();
+    let replayed: Vec<String> =
+        EventHandler::from_split(replay, tokio::io::sink(), String::new(), String::new())
+            .map(|msg| format!("{msg:?}"))
+            .collect()
+            .await;
+
+    assert!(
+        recorded.iter().any(|x| x.starts_with("Ok(Snapshot(")),
+        "{recorded:?}"
+    );
+    assert_eq!(recorded, replayed);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn capture_error_keeps_the_connection() {
+    let addr = mock(Script::new()).await;
+
+    // Every write fails with ENOSPC
+    let alma = Alma::new(addr, USER.to_string(), SECRET.to_string()).record("/dev/full");
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(alma.run(tx));
+
+    // The error can come before or after the snapshot, the connection isn't closed by it
+    let (mut capture_error, mut snapshot) = (false, false);
+    let wait = async {
+        while !(capture_error && snapshot) {
+            match rx.recv().await.unwrap() {
+                AlmaEvent::Error(AmiError::Capture(_)) => capture_error = true,
+                AlmaEvent::Error(er) => panic!("{er}"),
+                AlmaEvent::Connection(ConnectionState::Disconnected) => panic!("Disconnected"),
+                AlmaEvent::Message(msg) if matches!(*msg, AmiMessage::Snapshot(_)) => {
+                    snapshot = true
+                }
+                _ => {}
+            }
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), wait)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,93 @@
+use asterisk_queue_handler_events::asterisk::{
+    error::AmiError,
+    event::{AmiMessage, Charset, EventHandler},
+};
+use futures::StreamExt;
+use tokio::io::{AsyncWriteExt, DuplexStream, Sink};
+
+type Handler = EventHandler<DuplexStream, Sink>;
+
+/// Frames read by the handler when the server sends `data` one byte at a time
+async fn read(
+    data: &'static [u8],
+    configure: impl FnOnce(Handler) -> Handler,
+) -> Vec<Result<AmiMessage, AmiError>> {
+    let (mut server, client) = tokio::io::duplex(1);
+    tokio::spawn(async move {
+        server.write_all(data).await.unwrap();
+    });
+
+    let handler = EventHandler::from_split(client, tokio::io::sink(), String::new(), String::new());
+    configure(handler).collect().await
+}
+
+async fn read_all(data: &'static [u8], max_frame_size: usize) -> Vec<Result<AmiMessage, AmiError>> {
+    read(data, |x| x.max_frame_size(max_frame_size)).await
+}
+
+#[tokio::test]
+async fn frames_split_at_every_byte() {
+    let data = b"Asterisk Call Manager/7.0.3\r\n\
+        Event: QueueCallerJoin\r\nQueue: ventas\r\nUniqueid: 1.1\r\nPosition: 1\r\n\r\n\
+        Event: QueueCallerLeave\nQueue: ventas\nUniqueid: 1.1\nPosition: 1\n\n\
+        Event: QueueCallerJoin\r\nQueue: ventas\r\nUniqueid: 1.2\r\nPosition: 1";
+
+    let messages = read_all(data, 1024).await;
+    assert_eq!(messages.len(), 4, "{messages:?}");
+
+    let Ok(AmiMessage::Greeting(greeting)) = &messages[0] else {
+        panic!("{:?}", messages[0]);
+    };
+    assert_eq!(greeting.version.map(|x| x.major), Some(7));
+    assert!(matches!(&messages[1], Ok(AmiMessage::CallerJoin(x)) if x.callet_unique_id == "1.1"));
+    assert!(matches!(&messages[2], Ok(AmiMessage::CallerLeave(x)) if x.callet_unique_id == "1.1"));
+    // The last frame without the blank line is read at the end of the stream
+    assert!(matches!(&messages[3], Ok(AmiMessage::CallerJoin(x)) if x.callet_unique_id == "1.2"));
+}
+
+#[tokio::test]
+async fn oversized_frame_is_skipped() {
+    let data = b"Asterisk Call Manager/7.0.3\r\n\
+        Event: QueueCallerJoin\r\nQueue: ventas\r\nPosition: 1\r\nUniqueid: 1.1\r\nCallerIDName: a very long name\r\n\r\n\
+        Event: QueueCallerLeave\r\nQueue: ventas\r\nPosition: 1\r\nUniqueid: 1.1\r\n\r\n";
+
+    let messages = read_all(data, 80).await;
+    assert_eq!(messages.len(), 3, "{messages:?}");
+    assert!(matches!(messages[0], Ok(AmiMessage::Greeting(_))));
+    assert!(matches!(
+        messages[1],
+        Err(AmiError::FrameTooLarge { limit: 80 })
+    ));
+    assert!(matches!(&messages[2], Ok(AmiMessage::CallerLeave(x)) if x.callet_unique_id == "1.1"));
+}
+
+/// Caller name in ISO-8859-1, the é is the byte 0xE9
+const LATIN1: &[u8] = b"Asterisk Call Manager/7.0.3\r\n\
+    Event: QueueCallerJoin\r\nQueue: ventas\r\nPosition: 1\r\nUniqueid: 1.1\r\nCallerIDName: Jos\xe9\r\n\r\n";
+
+/// The message of the frame and then the InvalidUtf8 error
+async fn read_latin1(charset: Charset) -> (String, String) {
+    let messages = read(LATIN1, |x| x.charset(charset)).await;
+    assert_eq!(messages.len(), 3, "{messages:?}");
+    let Ok(AmiMessage::CallerJoin(caller)) = &messages[1] else {
+        panic!("{:?}", messages[1]);
+    };
+    let Err(AmiError::InvalidUtf8 { frame, .. }) = &messages[2] else {
+        panic!("{:?}", messages[2]);
+    };
+    (caller.caller_id_name.clone(), frame.clone())
+}
+
+#[tokio::test]
+async fn latin1_frame() {
+    let (name, frame) = read_latin1(Charset::Latin1).await;
+    assert_eq!(name, "José");
+    assert!(frame.contains("CallerIDName: José"), "{frame}");
+}
+
+#[tokio::test]
+async fn lossy_frame() {
+    let (name, frame) = read_latin1(Charset::Utf8Lossy).await;
+    assert_eq!(name, "Jos\u{FFFD}");
+    assert!(frame.contains("CallerIDName: Jos\u{FFFD}"), "{frame}");
+}
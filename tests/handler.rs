@@ -0,0 +1,379 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use asterisk_queue_handler_events::asterisk::{
+    error::AmiError,
+    event::{AmiMessage, EventHandler, LoginMode},
+};
+use futures::StreamExt;
+use tokio::{
+    io::{
+        AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, DuplexStream, ReadHalf,
+        WriteHalf,
+    },
+    sync::mpsc::{self, UnboundedReceiver},
+};
+
+const SECRET: &str = "secret";
+
+/// Server side of the duplex, it reads the actions and writes the answers
+struct Server {
+    reader: BufReader<ReadHalf<DuplexStream>>,
+    writer: WriteHalf<DuplexStream>,
+}
+
+impl Server {
+    /// Headers of the next action
+    async fn action(&mut self) -> Vec<(String, String)> {
+        let mut headers = Vec::new();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            assert!(
+                self.reader.read_line(&mut line).await.unwrap() > 0,
+                "closed"
+            );
+            let line = line.trim_end();
+            if line.is_empty() {
+                return headers;
+            }
+            let (key, value) = line.split_once(':').unwrap();
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    /// Next action, it must be `name`. Returns its headers and ActionID
+    async fn expect(&mut self, name: &str) -> (Vec<(String, String)>, String) {
+        let headers = self.action().await;
+        assert_eq!(get(&headers, "Action"), Some(name), "{headers:?}");
+        let id = get(&headers, "ActionID").unwrap().to_string();
+        (headers, id)
+    }
+
+    async fn send(&mut self, data: &str) {
+        self.writer.write_all(data.as_bytes()).await.unwrap();
+    }
+
+    async fn success(&mut self, id: &str, headers: &str) {
+        self.send(&format!(
+            "Response: Success\r\nActionID: {id}\r\n{headers}\r\n"
+        ))
+        .await;
+    }
+}
+
+fn get<'a>(headers: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, v)| v.as_str())
+}
+
+/// Writer whose flush is pending once, like TLS with a full socket
+struct PendingFlush<W> {
+    inner: W,
+    pending: bool,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for PendingFlush<W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        if std::mem::take(&mut self.pending) {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+fn handler<R: AsyncRead + Unpin, W: AsyncWrite + Unpin>(
+    reader: R,
+    writer: W,
+) -> EventHandler<R, W> {
+    EventHandler::from_split(reader, writer, "admin".to_string(), SECRET.to_string())
+}
+
+/// Handler polled in a task, its messages are sent to the receiver
+async fn start(login_mode: LoginMode) -> (Server, UnboundedReceiver<Result<AmiMessage, AmiError>>) {
+    start_with(|reader, writer| handler(reader, writer).login_mode(login_mode)).await
+}
+
+/// Same as `start` with the handler built by `build` over the client side of the duplex
+async fn start_with<R, W>(
+    build: impl FnOnce(ReadHalf<DuplexStream>, WriteHalf<DuplexStream>) -> EventHandler<R, W>,
+) -> (Server, UnboundedReceiver<Result<AmiMessage, AmiError>>)
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let (reader, writer) = tokio::io::split(client);
+    let mut handler = build(reader, writer);
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Some(msg) = handler.next().await {
+            if tx.send(msg).is_err() {
+                return;
+            }
+        }
+    });
+
+    let (reader, writer) = tokio::io::split(server);
+    let mut server = Server {
+        reader: BufReader::new(reader),
+        writer,
+    };
+    server.send("Asterisk Call Manager/9.0.0\r\n").await;
+    (server, rx)
+}
+
+async fn greeting(rx: &mut UnboundedReceiver<Result<AmiMessage, AmiError>>) {
+    let Some(Ok(AmiMessage::Greeting(greeting))) = rx.recv().await else {
+        panic!("no greeting");
+    };
+    assert_eq!(greeting.version.map(|x| x.asterisk()), Some(20));
+}
+
+/// Events and QueueStatus after the login
+async fn subscribe_and_snapshot(
+    server: &mut Server,
+    rx: &mut UnboundedReceiver<Result<AmiMessage, AmiError>>,
+) {
+    let (headers, id) = server.expect("Events").await;
+    assert_eq!(get(&headers, "EventMask"), Some("queue,agent"));
+    server.success(&id, "Events: On\r\n").await;
+
+    let (_, id) = server.expect("QueueStatus").await;
+    server
+        .success(
+            &id,
+            "EventList: start\r\nMessage: Queue status will follow\r\n",
+        )
+        .await;
+    server
+        .send(&format!(
+            "Event: QueueParams\r\nQueue: ventas\r\nStrategy: rrmemory\r\nCalls: 1\r\nActionID: {id}\r\n\r\n\
+             Event: QueueMember\r\nQueue: ventas\r\nName: Agent 100\r\nLocation: PJSIP/100\r\n\
+             StateInterface: PJSIP/100\r\nStatus: 1\r\nPaused: 0\r\nActionID: {id}\r\n\r\n\
+             Event: QueueEntry\r\nQueue: ventas\r\nPosition: 1\r\nUniqueid: 1.1\r\nWait: 10\r\nActionID: {id}\r\n\r\n\
+             Event: QueueStatusComplete\r\nEventList: Complete\r\nListItems: 3\r\nActionID: {id}\r\n\r\n"
+        ))
+        .await;
+
+    let Some(Ok(AmiMessage::Snapshot(snapshot))) = rx.recv().await else {
+        panic!("no snapshot");
+    };
+    assert!(snapshot.is_complete());
+    assert_eq!(snapshot.params.len(), 1);
+    assert_eq!(snapshot.params[0].calls, 1);
+    assert_eq!(snapshot.members.len(), 1);
+    assert_eq!(snapshot.entries.len(), 1);
+}
+
+#[tokio::test]
+async fn plain_login() {
+    let (mut server, mut rx) = start(LoginMode::Plain).await;
+    greeting(&mut rx).await;
+
+    let (headers, id) = server.expect("Login").await;
+    assert_eq!(get(&headers, "Username"), Some("admin"));
+    assert_eq!(get(&headers, "Secret"), Some(SECRET));
+    assert_eq!(get(&headers, "AuthType"), None);
+    server
+        .success(&id, "Message: Authentication accepted\r\n")
+        .await;
+
+    subscribe_and_snapshot(&mut server, &mut rx).await;
+}
+
+#[tokio::test]
+async fn md5_login() {
+    let (mut server, mut rx) = start(LoginMode::Md5).await;
+    greeting(&mut rx).await;
+
+    let (headers, id) = server.expect("Challenge").await;
+    assert_eq!(get(&headers, "AuthType"), Some("MD5"));
+    server.success(&id, "Challenge: 840152613\r\n").await;
+
+    let (headers, id) = server.expect("Login").await;
+    let key = format!("{:x}", md5::compute(format!("840152613{SECRET}")));
+    assert_eq!(get(&headers, "AuthType"), Some("MD5"));
+    assert_eq!(get(&headers, "Key"), Some(key.as_str()));
+    assert_eq!(get(&headers, "Secret"), None);
+    server
+        .success(&id, "Message: Authentication accepted\r\n")
+        .await;
+
+    subscribe_and_snapshot(&mut server, &mut rx).await;
+}
+
+#[tokio::test]
+async fn rejected_login() {
+    let (mut server, mut rx) = start(LoginMode::Plain).await;
+    greeting(&mut rx).await;
+
+    let (_, id) = server.expect("Login").await;
+    server
+        .send(&format!(
+            "Response: Error\r\nActionID: {id}\r\nMessage: Authentication failed\r\n\r\n"
+        ))
+        .await;
+
+    assert!(matches!(
+        rx.recv().await,
+        Some(Err(AmiError::AuthenticationRejected(message))) if message == "Authentication failed"
+    ));
+    // The handler ends, nothing else is sent
+    assert!(rx.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn pending_flush_keeps_the_connection() {
+    let (mut server, mut rx) = start_with(|reader, inner| {
+        handler(
+            reader,
+            PendingFlush {
+                inner,
+                pending: true,
+            },
+        )
+    })
+    .await;
+    greeting(&mut rx).await;
+
+    let (_, id) = server.expect("Login").await;
+    server
+        .success(&id, "Message: Authentication accepted\r\n")
+        .await;
+
+    subscribe_and_snapshot(&mut server, &mut rx).await;
+}
+
+/// Plain login and Events accepted, returns the ActionID of the QueueStatus
+async fn queue_status(
+    server: &mut Server,
+    rx: &mut UnboundedReceiver<Result<AmiMessage, AmiError>>,
+) -> String {
+    greeting(rx).await;
+    let (_, id) = server.expect("Login").await;
+    server
+        .success(&id, "Message: Authentication accepted\r\n")
+        .await;
+    let (_, id) = server.expect("Events").await;
+    server.success(&id, "Events: On\r\n").await;
+    let (_, id) = server.expect("QueueStatus").await;
+    server.success(&id, "EventList: start\r\n").await;
+    id
+}
+
+#[tokio::test]
+async fn stale_snapshot_is_requested_again() {
+    let (mut server, mut rx) =
+        start_with(|reader, writer| handler(reader, writer).resync(Duration::from_millis(100)))
+            .await;
+    let id = queue_status(&mut server, &mut rx).await;
+
+    // QueueStatusComplete is lost
+    server
+        .send(&format!(
+            "Event: QueueParams\r\nQueue: ventas\r\nActionID: {id}\r\n\r\n"
+        ))
+        .await;
+    let result = rx.recv().await;
+    assert!(
+        matches!(
+            result,
+            Some(Err(AmiError::IncompleteSnapshot {
+                expected: None,
+                received: 1
+            }))
+        ),
+        "{result:?}"
+    );
+
+    let (_, id) = server.expect("QueueStatus").await;
+    server
+        .send(&format!(
+            "Response: Success\r\nEventList: start\r\nActionID: {id}\r\n\r\n\
+             Event: QueueStatusComplete\r\nEventList: Complete\r\nListItems: 0\r\nActionID: {id}\r\n\r\n"
+        ))
+        .await;
+    let result = rx.recv().await;
+    assert!(
+        matches!(&result, Some(Ok(AmiMessage::Snapshot(x))) if x.is_complete()),
+        "{result:?}"
+    );
+}
+
+#[tokio::test]
+async fn invalid_list_event_fails_the_snapshot() {
+    let (mut server, mut rx) = start(LoginMode::Plain).await;
+    let id = queue_status(&mut server, &mut rx).await;
+
+    server
+        .send(&format!(
+            "Event: QueueEntry\r\nQueue: ventas\r\nUniqueid: 1.1\r\nActionID: {id}\r\n\r\n"
+        ))
+        .await;
+    let result = rx.recv().await;
+    assert!(
+        matches!(&result, Some(Err(AmiError::MissingField(key))) if key == "Position"),
+        "{result:?}"
+    );
+    let result = rx.recv().await;
+    assert!(
+        matches!(
+            result,
+            Some(Err(AmiError::IncompleteSnapshot {
+                expected: None,
+                received: 0
+            }))
+        ),
+        "{result:?}"
+    );
+
+    // The rest of the list isn't waited for anymore
+    server
+        .send(&format!(
+            "Event: QueueStatusComplete\r\nEventList: Complete\r\nListItems: 1\r\nActionID: {id}\r\n\r\n"
+        ))
+        .await;
+    let result = rx.recv().await;
+    assert!(
+        matches!(result, Some(Ok(AmiMessage::StatusComplete(_)))),
+        "{result:?}"
+    );
+}
+
+#[tokio::test]
+async fn login_timeout_ends_the_stream() {
+    let (mut server, mut rx) = start_with(|reader, writer| {
+        handler(reader, writer).login_timeout(Duration::from_millis(100))
+    })
+    .await;
+    greeting(&mut rx).await;
+
+    // The Login is never answered
+    server.expect("Login").await;
+    let result = rx.recv().await;
+    assert!(
+        matches!(&result, Some(Err(AmiError::Timeout(action))) if action == "Login"),
+        "{result:?}"
+    );
+    assert!(rx.recv().await.is_none());
+}
@@ -0,0 +1,498 @@
+use std::time::Duration;
+
+use asterisk_queue_handler_events::{
+    asterisk::{
+        Alma, AlmaEvent,
+        action::ActionResponse,
+        connection::{Backoff, ConnectionState},
+        entities::member::Member,
+        error::AmiError,
+        event::AmiMessage,
+        queue_action::{QueueAdd, QueuePause, QueueRemove},
+        transport::{TlsConfig, Transport},
+    },
+    mock::{Frame, MockConfig, MockServer, Script},
+};
+use tokio::{
+    io::AsyncWriteExt,
+    sync::mpsc::{self, UnboundedReceiver},
+};
+
+const USER: &str = "admin";
+const SECRET: &str = "secret";
+
+async fn start(config: MockConfig) -> (String, MockServer) {
+    let server = MockServer::bind("127.0.0.1:0", config).await.unwrap();
+    (server.local_addr().unwrap().to_string(), server)
+}
+
+fn alma(addr: String, secret: &str) -> Alma {
+    Alma::new(addr, USER.to_string(), secret.to_string()).backoff(Backoff::new(
+        Duration::from_millis(10),
+        Duration::from_millis(50),
+    ))
+}
+
+/// Events until one matches, it fails after 5 seconds
+async fn wait_for(
+    rx: &mut UnboundedReceiver<AlmaEvent>,
+    mut f: impl FnMut(&AlmaEvent) -> bool,
+) -> Vec<AlmaEvent> {
+    let mut events = Vec::new();
+    let wait = async {
+        while let Some(event) = rx.recv().await {
+            let done = f(&event);
+            events.push(event);
+            if done {
+                return;
+            }
+        }
+        panic!("Alma stopped: {events:?}");
+    };
+    if tokio::time::timeout(Duration::from_secs(5), wait)
+        .await
+        .is_err()
+    {
+        panic!("Timeout: {events:?}");
+    }
+    events
+}
+
+fn has_connection(events: &[AlmaEvent], state: ConnectionState) -> bool {
+    events
+        .iter()
+        .any(|x| matches!(x, AlmaEvent::Connection(x) if *x == state))
+}
+
+fn is_snapshot(event: &AlmaEvent) -> bool {
+    matches!(event, AlmaEvent::Message(msg) if matches!(**msg, AmiMessage::Snapshot(_)))
+}
+
+#[tokio::test]
+async fn login() {
+    let (addr, mut server) = start(MockConfig::new(USER, SECRET)).await;
+    let mut received = server.received().unwrap();
+    server.spawn();
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(alma(addr, SECRET).run(tx));
+    let events = wait_for(&mut rx, is_snapshot).await;
+
+    assert!(has_connection(&events, ConnectionState::Connected));
+    assert!(!events.iter().any(|x| matches!(x, AlmaEvent::Error(_))));
+
+    let login = received.recv().await.unwrap();
+    assert_eq!(login.get("Action"), Some("Login"));
+    assert_eq!(login.get("Username"), Some(USER));
+    assert_eq!(login.get("Secret"), Some(SECRET));
+    assert_eq!(received.recv().await.unwrap().get("Action"), Some("Events"));
+    assert_eq!(
+        received.recv().await.unwrap().get("Action"),
+        Some("QueueStatus")
+    );
+}
+
+#[tokio::test]
+async fn rejected_login() {
+    let (addr, server) = start(MockConfig::new(USER, SECRET)).await;
+    server.spawn();
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let alma = tokio::spawn(alma(addr, "wrong").stop_on_auth_failure(true).run(tx));
+    let events = wait_for(&mut rx, |x| {
+        matches!(x, AlmaEvent::Connection(ConnectionState::Disconnected))
+    })
+    .await;
+
+    assert!(matches!(
+        events.iter().rev().nth(1),
+        Some(AlmaEvent::Error(AmiError::AuthenticationRejected(message))) if message == "Authentication failed"
+    ));
+    // Nothing is retried with the same credentials
+    tokio::time::timeout(Duration::from_secs(1), alma)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(rx.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn snapshot() {
+    let (addr, server) = start(MockConfig::new(USER, SECRET)).await;
+    server.spawn();
+
+    let alma = alma(addr, SECRET);
+    let state = alma.state();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(alma.run(tx));
+    wait_for(&mut rx, is_snapshot).await;
+
+    assert_eq!(state.queue_names(), vec!["ventas".to_string()]);
+    let queue = state.queue("ventas").unwrap();
+    assert_eq!(queue.params.service_level, 60);
+    assert_eq!(queue.members.len(), 2);
+    // By the Location, not by the StateInterface
+    assert!(queue.members.contains_key("Local/100@from-queue/n"));
+    assert!(queue.callers.is_empty());
+}
+
+#[tokio::test]
+async fn pause_a_member_of_the_snapshot() {
+    let (addr, server) = start(MockConfig::new(USER, SECRET)).await;
+    server.spawn();
+
+    let alma = alma(addr, SECRET);
+    let state = alma.state();
+    let client = alma.client();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(alma.run(tx));
+    wait_for(&mut rx, is_snapshot).await;
+
+    let member = state.queue("ventas").unwrap().members["Local/100@from-queue/n"].clone();
+    let response = client
+        .send_action(QueuePause::from_member(&member, true))
+        .await
+        .unwrap();
+    assert!(response.response.is_ok());
+    let [AmiMessage::MemberPaused(paused)] = response.events.as_slice() else {
+        panic!("{:?}", response.events);
+    };
+    assert_eq!(paused.interface, "Local/100@from-queue/n");
+
+    wait_for(
+        &mut rx,
+        |x| matches!(x, AlmaEvent::Message(msg) if matches!(**msg, AmiMessage::MemberPaused(_))),
+    )
+    .await;
+    let queue = state.queue("ventas").unwrap();
+    assert_eq!(queue.members.len(), 2);
+    assert!(queue.members["Local/100@from-queue/n"].paused);
+}
+
+/// The follow-up event of the action, the only one in the response
+fn follow_up(response: &ActionResponse) -> &Member {
+    assert!(response.response.is_ok(), "{:?}", response.response);
+    match response.events.as_slice() {
+        [
+            AmiMessage::MemberPaused(member)
+            | AmiMessage::MemberAdded(member)
+            | AmiMessage::MemberRemoved(member),
+        ] => member,
+        events => panic!("{events:?}"),
+    }
+}
+
+#[tokio::test]
+async fn member_actions_resolve_with_their_event() {
+    let (addr, server) = start(MockConfig::new(USER, SECRET)).await;
+    server.spawn();
+
+    let alma = alma(addr, SECRET);
+    let state = alma.state();
+    let client = alma.client();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(alma.run(tx));
+    wait_for(&mut rx, is_snapshot).await;
+
+    let interface = "Local/300@from-queue/n";
+    let add = QueueAdd::new("ventas", interface)
+        .member_name("Agent 300")
+        .state_interface("PJSIP/300");
+    let response = client.send_action(add).await.unwrap();
+    let member = follow_up(&response);
+    assert!(matches!(response.events[0], AmiMessage::MemberAdded(_)));
+    assert_eq!(
+        (member.queue.as_str(), member.interface.as_str()),
+        ("ventas", interface)
+    );
+    assert_eq!(member.member_name, "Agent 300");
+
+    // Without queue the first QueueMemberPause of the interface resolves it
+    let response = client
+        .send_action(QueuePause::new(interface, true).reason("lunch"))
+        .await
+        .unwrap();
+    let member = follow_up(&response);
+    assert!(member.paused);
+    assert_eq!(member.pause_reason, "lunch");
+
+    let response = client
+        .send_action(QueueRemove::new("ventas", interface))
+        .await
+        .unwrap();
+    assert!(matches!(response.events[0], AmiMessage::MemberRemoved(_)));
+    assert_eq!(follow_up(&response).interface, interface);
+
+    wait_for(
+        &mut rx,
+        |x| matches!(x, AlmaEvent::Message(msg) if matches!(**msg, AmiMessage::MemberRemoved(_))),
+    )
+    .await;
+    let members = state.queue("ventas").unwrap().members;
+    assert_eq!(members.len(), 2);
+    assert!(!members.contains_key(interface));
+}
+
+#[tokio::test]
+async fn queue_summary() {
+    // ventas: two available members; soporte: one member paused and two callers
+    let mut snapshot = MockConfig::default_snapshot("ventas");
+    snapshot.extend([
+        Frame::event("QueueParams")
+            .header("Queue", "soporte")
+            .header("Calls", "2")
+            .header("Holdtime", "30")
+            .header("TalkTime", "120"),
+        Frame::event("QueueMember")
+            .header("Queue", "soporte")
+            .header("Location", "Local/200@from-queue/n")
+            .header("Status", "1")
+            .header("Paused", "1"),
+    ]);
+    let (addr, server) = start(MockConfig::new(USER, SECRET).snapshot(snapshot)).await;
+    server.spawn();
+
+    let alma = alma(addr, SECRET);
+    let client = alma.client();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(alma.run(tx));
+    wait_for(&mut rx, is_snapshot).await;
+
+    let mut summary = client.queue_summary(None).await.unwrap();
+    summary.sort_by(|a, b| a.queue.cmp(&b.queue));
+    let queues: Vec<_> = summary
+        .iter()
+        .map(|x| (x.queue.as_str(), x.logged_in, x.available, x.callers))
+        .collect();
+    assert_eq!(queues, [("soporte", 1, 0, 2), ("ventas", 2, 2, 0)]);
+    assert_eq!(summary[0].hold_time, 30);
+    assert_eq!(summary[0].talk_time, 120);
+
+    let summary = client.queue_summary(Some("soporte")).await.unwrap();
+    assert_eq!(summary.len(), 1);
+    assert_eq!(summary[0].queue, "soporte");
+
+    let summary = client.queue_summary(Some("ventas")).await.unwrap();
+    assert_eq!(summary.len(), 1);
+    assert_eq!(summary[0].available, 2);
+}
+
+#[tokio::test]
+async fn caller_join_and_abandon() {
+    let script = Script::new()
+        // The snapshot is applied before the live events
+        .wait(Duration::from_millis(100))
+        .caller_join("ventas", "1.1", "1001", 1)
+        .caller_join("ventas", "1.2", "1002", 2)
+        .caller_abandon("ventas", "1.1", 1, 5);
+    let (addr, server) = start(MockConfig::new(USER, SECRET).script(script)).await;
+    server.spawn();
+
+    let alma = alma(addr, SECRET);
+    let state = alma.state();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(alma.run(tx));
+    wait_for(&mut rx, |x| {
+        matches!(x, AlmaEvent::Message(msg) if matches!(&**msg, AmiMessage::CallerLeave(caller) if caller.callet_unique_id == "1.1"))
+    })
+    .await;
+
+    let queue = state.queue("ventas").unwrap();
+    assert_eq!(queue.params.abandoned, 1);
+    assert_eq!(queue.params.calls, 1);
+    assert_eq!(queue.callers.len(), 1);
+    assert_eq!(queue.callers[0].unique_id, "1.2");
+    assert_eq!(queue.callers[0].position, 1);
+}
+
+#[tokio::test]
+async fn reconnect() {
+    let script = Script::new()
+        .wait(Duration::from_millis(100))
+        .caller_join("ventas", "1.1", "1001", 1)
+        .agent_connect("ventas", "PJSIP/100", "1.1", 3)
+        .wait(Duration::from_millis(200))
+        .close();
+    let (addr, server) = start(MockConfig::new(USER, SECRET).script(script)).await;
+    server.spawn();
+
+    let alma = alma(addr, SECRET);
+    let state = alma.state();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(alma.run(tx));
+
+    wait_for(
+        &mut rx,
+        |x| matches!(x, AlmaEvent::Message(msg) if matches!(**msg, AmiMessage::AgentConnect(_))),
+    )
+    .await;
+    assert_eq!(state.agent_calls().len(), 1);
+
+    let events = wait_for(&mut rx, |x| {
+        matches!(
+            x,
+            AlmaEvent::Connection(ConnectionState::Reconnecting { .. })
+        )
+    })
+    .await;
+    assert!(has_connection(&events, ConnectionState::Disconnected));
+    // The AgentComplete can be lost while disconnected
+    assert!(state.agent_calls().is_empty());
+    assert_eq!(state.queue("ventas").unwrap().callers.len(), 1);
+
+    // The new connection logs in again and the snapshot replaces the state
+    let events = wait_for(&mut rx, is_snapshot).await;
+    assert!(has_connection(&events, ConnectionState::Connected));
+    assert!(state.queue("ventas").unwrap().callers.is_empty());
+}
+
+/// Delays of the first `count` reconnections
+async fn reconnections(
+    rx: &mut UnboundedReceiver<AlmaEvent>,
+    count: usize,
+) -> Vec<(u32, Duration)> {
+    let mut delays = Vec::new();
+    while delays.len() < count {
+        let events = wait_for(rx, |x| {
+            matches!(
+                x,
+                AlmaEvent::Connection(ConnectionState::Reconnecting { .. })
+            )
+        })
+        .await;
+        if let Some(AlmaEvent::Connection(ConnectionState::Reconnecting { attempt, delay })) =
+            events.last()
+        {
+            delays.push((*attempt, *delay));
+        }
+    }
+    delays
+}
+
+/// Listener that keeps the connections open and never answers, like a half-open PBX.
+/// Only the banner is sent when `banner` is true
+async fn silent_server(banner: bool) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        let mut streams = Vec::new();
+        while let Ok((mut stream, _)) = listener.accept().await {
+            if banner {
+                _ = stream.write_all(b"Asterisk Call Manager/7.0.3\r\n").await;
+            }
+            streams.push(stream);
+        }
+    });
+    addr
+}
+
+fn is_reconnecting(event: &AlmaEvent) -> bool {
+    matches!(
+        event,
+        AlmaEvent::Connection(ConnectionState::Reconnecting { .. })
+    )
+}
+
+fn has_timeout(events: &[AlmaEvent], action: &str) -> bool {
+    events
+        .iter()
+        .any(|x| matches!(x, AlmaEvent::Error(AmiError::Timeout(x)) if x == action))
+}
+
+#[tokio::test]
+async fn login_timeout_reconnects() {
+    let addr = silent_server(true).await;
+    let alma = alma(addr, SECRET).login_timeout(Duration::from_millis(100));
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(alma.run(tx));
+
+    let events = wait_for(&mut rx, is_reconnecting).await;
+    assert!(has_connection(&events, ConnectionState::Connected));
+    assert!(has_timeout(&events, "Login"), "{events:?}");
+    assert!(has_connection(&events, ConnectionState::Disconnected));
+}
+
+#[tokio::test]
+async fn connect_timeout_reconnects() {
+    // The TLS handshake never ends
+    let addr = silent_server(false).await;
+    let alma = alma(addr, SECRET)
+        .transport(Transport::Tls(TlsConfig::default()))
+        .connect_timeout(Duration::from_millis(100));
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(alma.run(tx));
+
+    let events = wait_for(&mut rx, is_reconnecting).await;
+    assert!(has_timeout(&events, "the connection"), "{events:?}");
+    assert!(!has_connection(&events, ConnectionState::Connected));
+}
+
+#[tokio::test]
+async fn backoff_grows_when_closed_before_login() {
+    // The server sends the banner and closes, e.g. a session limit
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            _ = stream.write_all(b"Asterisk Call Manager/7.0.3\r\n").await;
+        }
+    });
+
+    let backoff = Backoff::new(Duration::from_millis(10), Duration::from_secs(1)).jitter(0.0);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(alma(addr, SECRET).backoff(backoff).run(tx));
+
+    let delays = reconnections(&mut rx, 3).await;
+    assert_eq!(
+        delays,
+        vec![
+            (1, Duration::from_millis(10)),
+            (2, Duration::from_millis(20)),
+            (3, Duration::from_millis(40)),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn backoff_resets_after_login() {
+    let script = Script::new().wait(Duration::from_millis(50)).close();
+    let (addr, server) = start(MockConfig::new(USER, SECRET).script(script)).await;
+    server.spawn();
+
+    let backoff = Backoff::new(Duration::from_millis(10), Duration::from_secs(1)).jitter(0.0);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(alma(addr, SECRET).backoff(backoff).run(tx));
+
+    let delays = reconnections(&mut rx, 3).await;
+    assert!(
+        delays.iter().all(|x| *x == (1, Duration::from_millis(10))),
+        "{delays:?}"
+    );
+}
+
+#[tokio::test]
+async fn session_errors_are_reported() {
+    let (addr, mut server) = start(MockConfig::new(USER, SECRET)).await;
+    let mut received = server.received().unwrap();
+    let mut errors = server.errors().unwrap();
+    server.spawn();
+
+    // The client resets the connection after the server read the action
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"Action: Ping\r\n\r\n").await.unwrap();
+    received.recv().await.unwrap();
+    stream.set_zero_linger().unwrap();
+    drop(stream);
+
+    let er = tokio::time::timeout(Duration::from_secs(5), errors.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(
+        matches!(
+            er.kind(),
+            std::io::ErrorKind::ConnectionReset | std::io::ErrorKind::BrokenPipe
+        ),
+        "{er:?}"
+    );
+}
@@ -0,0 +1,106 @@
+use asterisk_queue_handler_events::asterisk::{
+    error::AmiError, event::AmiMessage, headers::Headers,
+};
+
+const MEMBER: &str = "Event: QueueMemberStatus\r\nQueue: ventas\r\nInterface: PJSIP/100\r\n\
+    Status: 1\r\nLastCall: 1700000000";
+
+#[test]
+fn invalid_optional_number_is_the_default() {
+    let frame = format!("{MEMBER}\r\nCallsTaken: many\r\nLastPause: -1");
+    let Ok(AmiMessage::MemberStatus(member)) = AmiMessage::try_from(frame.as_str()) else {
+        panic!("{frame}");
+    };
+    assert_eq!(member.calls_taken, 0);
+    assert_eq!(member.last_pause, 0);
+    assert_eq!(member.last_call, 1700000000);
+}
+
+#[test]
+fn counters_above_u16() {
+    let frame = format!("{MEMBER}\r\nCallsTaken: 70000");
+    let Ok(AmiMessage::MemberStatus(member)) = AmiMessage::try_from(frame.as_str()) else {
+        panic!("{frame}");
+    };
+    assert_eq!(member.calls_taken, 70000);
+}
+
+#[test]
+fn invalid_required_number_rejects_the_frame() {
+    let frame = "Event: QueueCallerJoin\r\nQueue: ventas\r\nPosition: first\r\nUniqueid: 1.1";
+    let result = AmiMessage::try_from(frame);
+    assert!(
+        matches!(&result, Err(AmiError::FieldParse { key, value }) if key == "Position" && value == "first"),
+        "{result:?}"
+    );
+
+    let frame = "Event: QueueStatusComplete\r\nListItems: ";
+    let result = AmiMessage::try_from(frame);
+    assert!(
+        matches!(&result, Err(AmiError::MissingField(key)) if key == "ListItems"),
+        "{result:?}"
+    );
+}
+
+const VARIABLES: &str = "Event: QueueCallerJoin\r\nQueue: ventas\r\nPosition: 1\r\n\
+    ChanVariable(CDR_ID): 10\r\nchanvariable: TENANT = acme\r\nCHANVARIABLE(lang): es\r\n\
+    Message: a: b";
+
+#[test]
+fn headers_keep_the_order_of_repeated_keys() {
+    let headers = Headers::parse(VARIABLES);
+    assert_eq!(headers.len(), 7);
+    assert_eq!(headers.get("event"), Some("QueueCallerJoin"));
+    assert_eq!(headers.get("QUEUE"), Some("ventas"));
+    assert_eq!(headers.get("Message"), Some("a: b"));
+    assert_eq!(headers.get("Missing"), None);
+
+    let headers = Headers::parse("Key: 1\r\nkey: 2\r\nOther: x\r\nKEY: 3\r\nwithout colon");
+    assert_eq!(headers.get_all("Key").collect::<Vec<_>>(), ["1", "2", "3"]);
+    assert_eq!(headers.get("key"), Some("1"));
+    assert_eq!(headers.len(), 4);
+}
+
+#[test]
+fn headers_variables_in_both_forms() {
+    let headers = Headers::parse(VARIABLES);
+    assert_eq!(
+        headers.variables("ChanVariable"),
+        [("CDR_ID", "10"), ("TENANT", "acme"), ("lang", "es")]
+    );
+    assert!(headers.variables("Variable").is_empty());
+
+    let Ok(AmiMessage::CallerJoin(caller)) = AmiMessage::try_from(VARIABLES) else {
+        panic!("{VARIABLES}");
+    };
+    assert_eq!(
+        caller.chan_variables,
+        [
+            ("CDR_ID".to_string(), "10".to_string()),
+            ("TENANT".to_string(), "acme".to_string()),
+            ("lang".to_string(), "es".to_string()),
+        ]
+    );
+}
+
+const DIAL: &str = "Event: DialBegin\r\nChannel: PJSIP/trunk-1\r\nUniqueid: 1.1\r\n\
+    DestChannel: PJSIP/100-2\r\ndestuniqueid: 1.2\r\nDest: nothing\r\nDialString: 100";
+
+#[test]
+fn headers_prefixed() {
+    let headers = Headers::parse(DIAL);
+    let dest = headers.prefixed("Dest");
+    assert_eq!(
+        dest.iter().collect::<Vec<_>>(),
+        [("Channel", "PJSIP/100-2"), ("uniqueid", "1.2")]
+    );
+    assert_eq!(dest.get("UniqueID"), Some("1.2"));
+
+    let Ok(AmiMessage::DialBegin(dial)) = AmiMessage::try_from(DIAL) else {
+        panic!("{DIAL}");
+    };
+    assert_eq!(dial.dest.channel, "PJSIP/100-2");
+    assert_eq!(dial.dest.unique_id, "1.2");
+    assert_eq!(dial.caller.map(|x| x.unique_id), Some("1.1".to_string()));
+    assert_eq!(dial.dial_string, "100");
+}
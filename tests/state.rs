@@ -0,0 +1,56 @@
+use asterisk_queue_handler_events::asterisk::{
+    entities::Params,
+    error::AmiError,
+    event::AmiMessage,
+    state::{QueueSnapshot, QueueState},
+};
+
+fn snapshot(queue: &str, list_items: Option<i32>) -> AmiMessage {
+    let params = Params {
+        queue: queue.to_string(),
+        ..Default::default()
+    };
+    AmiMessage::Snapshot(QueueSnapshot {
+        params: vec![params],
+        list_items,
+        received: 1,
+        ..Default::default()
+    })
+}
+
+#[test]
+fn complete_snapshot_replaces_the_queues() {
+    let state = QueueState::new();
+    state.apply(&snapshot("ventas", Some(1))).unwrap();
+    assert_eq!(state.queue_names(), vec!["ventas".to_string()]);
+}
+
+#[test]
+fn incomplete_snapshot_is_reported() {
+    let state = QueueState::new();
+    state.apply(&snapshot("ventas", Some(1))).unwrap();
+
+    let result = state.apply(&snapshot("soporte", Some(3)));
+    assert!(
+        matches!(
+            result,
+            Err(AmiError::IncompleteSnapshot {
+                expected: Some(3),
+                received: 1
+            })
+        ),
+        "{result:?}"
+    );
+
+    let result = state.apply(&snapshot("soporte", None));
+    assert!(matches!(
+        result,
+        Err(AmiError::IncompleteSnapshot {
+            expected: None,
+            received: 1
+        })
+    ));
+
+    // The state is the last complete snapshot
+    assert_eq!(state.queue_names(), vec!["ventas".to_string()]);
+}
@@ -0,0 +1,146 @@
+use std::{path::PathBuf, sync::Arc};
+
+use asterisk_queue_handler_events::asterisk::{
+    error::AmiError,
+    transport::{TlsConfig, Transport},
+};
+use rcgen::{BasicConstraints, CertificateParams, CertifiedKey, IsCa, Issuer, KeyPair};
+use tempfile::TempDir;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{ServerConfig, crypto::ring::default_provider, pki_types::PrivateKeyDer},
+};
+
+const BANNER: &[u8] = b"Asterisk Call Manager/7.0.3\r\n";
+
+/// Self-signed certificate for localhost
+fn self_signed() -> CertifiedKey<KeyPair> {
+    rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap()
+}
+
+/// Certificate for localhost signed by a new CA, returns the CA too
+fn signed_by_ca() -> (CertifiedKey<KeyPair>, rcgen::Certificate) {
+    let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca_key = KeyPair::generate().unwrap();
+    let ca = params.self_signed(&ca_key).unwrap();
+    let issuer = Issuer::new(params, ca_key);
+
+    let signing_key = KeyPair::generate().unwrap();
+    let cert = CertificateParams::new(vec!["localhost".to_string()])
+        .unwrap()
+        .signed_by(&signing_key, &issuer)
+        .unwrap();
+    (CertifiedKey { cert, signing_key }, ca)
+}
+
+fn write_pem(dir: &TempDir, name: &str, cert: &rcgen::Certificate) -> PathBuf {
+    let path = dir.path().join(name);
+    std::fs::write(&path, cert.pem()).unwrap();
+    path
+}
+
+/// TLS server that sends the banner to the first client, returns its address
+async fn server(certified: &CertifiedKey<KeyPair>) -> String {
+    let config = ServerConfig::builder_with_provider(Arc::new(default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![certified.cert.der().clone()],
+            PrivateKeyDer::Pkcs8(certified.signing_key.serialize_der().into()),
+        )
+        .unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        // It fails when the client rejects the certificate
+        if let Ok(mut stream) = acceptor.accept(stream).await {
+            _ = stream.write_all(BANNER).await;
+            _ = stream.flush().await;
+        }
+    });
+    addr
+}
+
+async fn connect(addr: &str, config: TlsConfig) -> Result<Vec<u8>, AmiError> {
+    let (mut reader, _writer) = Transport::Tls(config).connect(addr).await?;
+    let mut banner = vec![0; BANNER.len()];
+    reader.read_exact(&mut banner).await?;
+    Ok(banner)
+}
+
+#[tokio::test]
+async fn pinned_certificate_is_accepted() {
+    let dir = TempDir::new().unwrap();
+    let certified = self_signed();
+    let pinned = write_pem(&dir, "pinned.pem", &certified.cert);
+
+    // The address is an IP, the pinned certificate doesn't need to match the name
+    let addr = server(&certified).await;
+    let banner = connect(&addr, TlsConfig::default().pinned(pinned))
+        .await
+        .unwrap();
+    assert_eq!(banner, BANNER);
+}
+
+#[tokio::test]
+async fn other_certificate_is_rejected() {
+    let dir = TempDir::new().unwrap();
+    let pinned = write_pem(&dir, "pinned.pem", &self_signed().cert);
+
+    let addr = server(&self_signed()).await;
+    let result = connect(&addr, TlsConfig::default().pinned(pinned)).await;
+    assert!(result.is_err(), "{result:?}");
+}
+
+#[tokio::test]
+async fn certificate_signed_by_the_ca() {
+    let dir = TempDir::new().unwrap();
+    let (certified, ca) = signed_by_ca();
+    let ca = write_pem(&dir, "ca.pem", &ca);
+
+    let addr = server(&certified).await;
+    let config = TlsConfig::default().ca(ca).server_name("localhost");
+    let banner = connect(&addr, config).await.unwrap();
+    assert_eq!(banner, BANNER);
+}
+
+#[tokio::test]
+async fn certificate_of_other_ca_is_rejected() {
+    let dir = TempDir::new().unwrap();
+    let (_, ca) = signed_by_ca();
+    let ca = write_pem(&dir, "ca.pem", &ca);
+
+    let (certified, _) = signed_by_ca();
+    let addr = server(&certified).await;
+    let result = connect(&addr, TlsConfig::default().ca(ca).server_name("localhost")).await;
+    assert!(result.is_err(), "{result:?}");
+}
+
+#[tokio::test]
+async fn self_signed_certificate_is_rejected_without_pinning() {
+    let certified = self_signed();
+    let addr = server(&certified).await;
+    let result = connect(&addr, TlsConfig::default().server_name("localhost")).await;
+    assert!(result.is_err(), "{result:?}");
+}
+
+#[tokio::test]
+async fn invalid_pinned_certificate() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("pinned.pem");
+    std::fs::write(&path, b"not a certificate").unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let result = connect(&addr, TlsConfig::default().pinned(path)).await;
+    assert!(matches!(result, Err(AmiError::Tls(_))), "{result:?}");
+}